@@ -4,7 +4,7 @@ use cmake::Config;
 use std::env;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str;
 
@@ -111,11 +111,26 @@ fn build() -> io::Result<()> {
     config.define("NCNN_BUILD_EXAMPLES", "OFF");
     config.define("NCNN_BUILD_BENCHMARK", "OFF");
     config.define("NCNN_DISABLE_RTTI", "ON"); // Not used.
-    config.define("NCNN_PIXEL_DRAWING", "OFF"); // Not exposed by ncnn-rs.
-    config.define("NCNN_PIXEL_ROTATE", "OFF"); // Not exposed by ncnn-rs.
-    config.define("NCNN_PIXEL_AFFINE", "OFF"); // Not exposed by ncnn-rs.
     config.define("CMAKE_BUILD_TYPE", "Release");
 
+    if cfg!(feature = "pixel-drawing") {
+        config.define("NCNN_PIXEL_DRAWING", "ON");
+    } else {
+        config.define("NCNN_PIXEL_DRAWING", "OFF");
+    }
+
+    if cfg!(feature = "pixel-rotate") {
+        config.define("NCNN_PIXEL_ROTATE", "ON");
+    } else {
+        config.define("NCNN_PIXEL_ROTATE", "OFF");
+    }
+
+    if cfg!(feature = "pixel-affine") {
+        config.define("NCNN_PIXEL_AFFINE", "ON");
+    } else {
+        config.define("NCNN_PIXEL_AFFINE", "OFF");
+    }
+
     if cfg!(feature = "openmp") {
         config.define("NCNN_OPENMP", "ON");
     } else {
@@ -269,6 +284,10 @@ fn build_ncnn() -> Vec<PathBuf> {
         vec![output_dir().join("include").join("ncnn")]
     };
 
+    return include_paths;
+}
+
+fn link_ncnn() {
     if use_dynamic_linking() {
         println!("cargo:rustc-link-lib=dylib=ncnn");
     } else {
@@ -282,21 +301,41 @@ fn build_ncnn() -> Vec<PathBuf> {
     handle_openmp();
 
     handle_vulkan();
+}
 
-    return include_paths;
+// Builds C wrappers of ncnn C++ functions which are not part of c_api.h.
+fn build_ext(include_dir: &Path) {
+    println!("cargo:rerun-if-changed=src/c_api_ext.h");
+    println!("cargo:rerun-if-changed=src/c_api_ext.cpp");
+
+    // Linked before ncnn itself, static archives are order sensitive.
+    cc::Build::new()
+        .cpp(true)
+        .file("src/c_api_ext.cpp")
+        .include(include_dir)
+        .compile("ncnn_ext");
 }
 
 fn main() {
-    let include_paths = if let Ok(vcpkg_lib) = vcpkg::find_package("ncnn") {
-        vec![vcpkg_lib.include_paths[0].join("ncnn")]
+    let (include_paths, link) = if let Ok(vcpkg_lib) = vcpkg::find_package("ncnn") {
+        (vec![vcpkg_lib.include_paths[0].join("ncnn")], false)
     } else {
-        build_ncnn()
+        (build_ncnn(), true)
     };
 
     let header = search_include(&include_paths, "c_api.h");
+    let include_dir = Path::new(&header).parent().unwrap().to_path_buf();
+
+    build_ext(&include_dir);
+
+    if link {
+        link_ncnn();
+    }
 
     let bindings = bindgen::Builder::default()
         .header(header)
+        .header("src/c_api_ext.h")
+        .clang_arg(format!("-I{}", include_dir.display()))
         .allowlist_type("regex")
         .allowlist_function("ncnn.*")
         .allowlist_var("NCNN.*")
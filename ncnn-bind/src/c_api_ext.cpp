@@ -0,0 +1,519 @@
+#include "c_api_ext.h"
+
+#include "datareader.h"
+#include "layer.h"
+#include "mat.h"
+#include "modelbin.h"
+#include "net.h"
+#include "paramdict.h"
+
+#include <stdlib.h>
+#include <string.h>
+#include <vector>
+
+class DataReader_userdata : public ncnn::DataReader
+{
+public:
+    DataReader_userdata(ncnn_ext_datareader_scan_t _scan, ncnn_ext_datareader_read_t _read, void* _userdata)
+        : DataReader(), scan_func(_scan), read_func(_read), userdata(_userdata)
+    {
+    }
+#if NCNN_STRING
+    virtual int scan(const char* format, void* p) const
+    {
+        return scan_func(userdata, format, p);
+    }
+#endif /* NCNN_STRING */
+    virtual size_t read(void* buf, size_t size) const
+    {
+        return read_func(userdata, buf, size);
+    }
+
+public:
+    ncnn_ext_datareader_scan_t scan_func;
+    ncnn_ext_datareader_read_t read_func;
+    void* userdata;
+};
+
+ncnn_datareader_t ncnn_ext_datareader_create_userdata(ncnn_ext_datareader_scan_t scan, ncnn_ext_datareader_read_t read, void* userdata)
+{
+    /* same layout as ncnn_datareader_create, so ncnn_datareader_destroy releases it */
+    ncnn_datareader_t dr = (ncnn_datareader_t)malloc(sizeof(struct __ncnn_datareader_t));
+    dr->pthis = (void*)(new DataReader_userdata(scan, read, userdata));
+#if NCNN_STRING
+    dr->scan = 0;
+#endif /* NCNN_STRING */
+    dr->read = 0;
+    return dr;
+}
+
+// unlike ncnn::DataReaderFromMemory, stops at the end of the buffer
+class DataReaderFromMemory_bounded : public ncnn::DataReader
+{
+public:
+    DataReaderFromMemory_bounded(const unsigned char* _mem, size_t _size)
+        : DataReader(), mem(_mem), end(_mem + _size)
+    {
+    }
+
+    virtual size_t read(void* buf, size_t size) const
+    {
+        if (size > (size_t)(end - mem))
+            size = end - mem;
+        memcpy(buf, mem, size);
+        mem += size;
+        return size;
+    }
+
+    virtual size_t reference(size_t size, const void** buf) const
+    {
+        // unaligned or short data is left to read, which copies or fails
+        if ((size_t)mem % 4 != 0 || size > (size_t)(end - mem))
+            return 0;
+        *buf = mem;
+        mem += size;
+        return size;
+    }
+
+public:
+    mutable const unsigned char* mem;
+    const unsigned char* end;
+};
+
+ncnn_datareader_t ncnn_ext_datareader_create_from_memory(const unsigned char* mem, size_t size)
+{
+    ncnn_datareader_t dr = (ncnn_datareader_t)malloc(sizeof(struct __ncnn_datareader_t));
+    dr->pthis = (void*)(new DataReaderFromMemory_bounded(mem, size));
+#if NCNN_STRING
+    dr->scan = 0;
+#endif /* NCNN_STRING */
+    dr->read = 0;
+    return dr;
+}
+
+#if NCNN_STRING
+// ncnn keeps the int or float element type of arrays only when parsing text,
+// ParamDict::load_param is protected and reached through a subclass
+class ParamDict_text : public ncnn::ParamDict
+{
+public:
+    static int load(ncnn::ParamDict& pd, const ncnn::DataReader& dr)
+    {
+        int (ncnn::ParamDict::*load_param)(const ncnn::DataReader&) = &ParamDict_text::load_param;
+        return (pd.*load_param)(dr);
+    }
+};
+
+int ncnn_ext_paramdict_load_param(ncnn_paramdict_t pd, const char* text)
+{
+    const unsigned char* mem = (const unsigned char*)text;
+    ncnn::DataReaderFromMemory dr(mem);
+    return ParamDict_text::load(*(ncnn::ParamDict*)pd, dr);
+}
+#endif /* NCNN_STRING */
+
+class Layer_userdata : public ncnn::Layer
+{
+public:
+    Layer_userdata(ncnn_layer_t _layer, void* _userdata)
+        : Layer(), layer(_layer), ext_userdata(_userdata)
+    {
+    }
+
+    virtual int load_param(const ncnn::ParamDict& pd)
+    {
+        return layer->load_param(layer, (ncnn_paramdict_t)&pd);
+    }
+
+    virtual int load_model(const ncnn::ModelBin& mb);
+
+    virtual int create_pipeline(const ncnn::Option& opt)
+    {
+        return layer->create_pipeline(layer, (ncnn_option_t)&opt);
+    }
+
+    virtual int destroy_pipeline(const ncnn::Option& opt)
+    {
+        return layer->destroy_pipeline(layer, (ncnn_option_t)&opt);
+    }
+
+    virtual int forward(const ncnn::Mat& bottom_blob, ncnn::Mat& top_blob, const ncnn::Option& opt) const
+    {
+        ncnn_mat_t top_blob0 = 0;
+        int ret = layer->forward_1(layer, (ncnn_mat_t)&bottom_blob, &top_blob0, (ncnn_option_t)&opt);
+        if (top_blob0)
+        {
+            if (ret == 0)
+                top_blob = *(ncnn::Mat*)top_blob0;
+            ncnn_mat_destroy(top_blob0);
+        }
+        return ret;
+    }
+
+    virtual int forward(const std::vector<ncnn::Mat>& bottom_blobs, std::vector<ncnn::Mat>& top_blobs, const ncnn::Option& opt) const
+    {
+        const int n = (int)bottom_blobs.size();
+        const int n2 = (int)top_blobs.size();
+        std::vector<ncnn_mat_t> bottom_blobs0(n);
+        for (int i = 0; i < n; i++)
+        {
+            bottom_blobs0[i] = (ncnn_mat_t)&bottom_blobs[i];
+        }
+        std::vector<ncnn_mat_t> top_blobs0(n2, (ncnn_mat_t)0);
+        int ret = layer->forward_n(layer, bottom_blobs0.data(), n, top_blobs0.data(), n2, (ncnn_option_t)&opt);
+        for (int i = 0; i < n2; i++)
+        {
+            if (!top_blobs0[i])
+                continue;
+            if (ret == 0)
+                top_blobs[i] = *(ncnn::Mat*)top_blobs0[i];
+            ncnn_mat_destroy(top_blobs0[i]);
+        }
+        return ret;
+    }
+
+    virtual int forward_inplace(ncnn::Mat& bottom_top_blob, const ncnn::Option& opt) const
+    {
+        return layer->forward_inplace_1(layer, (ncnn_mat_t)&bottom_top_blob, (ncnn_option_t)&opt);
+    }
+
+    virtual int forward_inplace(std::vector<ncnn::Mat>& bottom_top_blobs, const ncnn::Option& opt) const
+    {
+        const int n = (int)bottom_top_blobs.size();
+        std::vector<ncnn_mat_t> bottom_top_blobs0(n);
+        for (int i = 0; i < n; i++)
+        {
+            bottom_top_blobs0[i] = (ncnn_mat_t)&bottom_top_blobs[i];
+        }
+        return layer->forward_inplace_n(layer, bottom_top_blobs0.data(), n, (ncnn_option_t)&opt);
+    }
+
+public:
+    ncnn_layer_t layer;
+    // ncnn::Layer::userdata is taken by the c api custom layer registry
+    void* ext_userdata;
+};
+
+static Layer_userdata* layer_of(const ncnn_layer_t layer)
+{
+    return (Layer_userdata*)layer->pthis;
+}
+
+static ncnn_mat_t modelbin_load_1d(const ncnn_modelbin_t mb, int w, int type)
+{
+    return (ncnn_mat_t)(new ncnn::Mat(((const ncnn::ModelBin*)mb->pthis)->load(w, type)));
+}
+
+static ncnn_mat_t modelbin_load_2d(const ncnn_modelbin_t mb, int w, int h, int type)
+{
+    return (ncnn_mat_t)(new ncnn::Mat(((const ncnn::ModelBin*)mb->pthis)->load(w, h, type)));
+}
+
+static ncnn_mat_t modelbin_load_3d(const ncnn_modelbin_t mb, int w, int h, int c, int type)
+{
+    return (ncnn_mat_t)(new ncnn::Mat(((const ncnn::ModelBin*)mb->pthis)->load(w, h, c, type)));
+}
+
+int Layer_userdata::load_model(const ncnn::ModelBin& mb)
+{
+    struct __ncnn_modelbin_t mb0;
+    mb0.pthis = (void*)&mb;
+    mb0.load_1d = modelbin_load_1d;
+    mb0.load_2d = modelbin_load_2d;
+    mb0.load_3d = modelbin_load_3d;
+    return layer->load_model(layer, &mb0);
+}
+
+// unlike ncnn::ModelBinFromMatArray, owns the weights and stops at the end of the array
+class ModelBinFromMatArray_owned : public ncnn::ModelBin
+{
+public:
+    ModelBinFromMatArray_owned(const std::vector<ncnn::Mat>& _weights)
+        : ModelBin(), weights(_weights), next(0)
+    {
+    }
+
+    virtual ncnn::Mat load(int w, int /*type*/) const
+    {
+        if (next >= weights.size())
+            return ncnn::Mat();
+        // a weight of another size would be read out of bounds by the layer
+        const ncnn::Mat& m = weights[next];
+        if (m.total() * m.elempack != (size_t)w)
+            return ncnn::Mat();
+        next++;
+        return m;
+    }
+
+public:
+    std::vector<ncnn::Mat> weights;
+    mutable size_t next;
+};
+
+ncnn_modelbin_t ncnn_ext_modelbin_create_from_mat_array(const ncnn_mat_t* weights, int n)
+{
+    std::vector<ncnn::Mat> weights0(n);
+    for (int i = 0; i < n; i++)
+    {
+        weights0[i] = *(const ncnn::Mat*)weights[i];
+    }
+
+    /* same layout as ncnn_modelbin_create_from_mat_array, so ncnn_modelbin_destroy releases it */
+    ncnn_modelbin_t mb = (ncnn_modelbin_t)malloc(sizeof(struct __ncnn_modelbin_t));
+    mb->pthis = (void*)(new ModelBinFromMatArray_owned(weights0));
+    mb->load_1d = modelbin_load_1d;
+    mb->load_2d = modelbin_load_2d;
+    mb->load_3d = modelbin_load_3d;
+    return mb;
+}
+
+static int layer_load_param(ncnn_layer_t layer, const ncnn_paramdict_t pd)
+{
+    return layer_of(layer)->ncnn::Layer::load_param(*(const ncnn::ParamDict*)pd);
+}
+
+static int layer_load_model(ncnn_layer_t layer, const ncnn_modelbin_t mb)
+{
+    return layer_of(layer)->ncnn::Layer::load_model(*(const ncnn::ModelBin*)mb->pthis);
+}
+
+static int layer_create_pipeline(ncnn_layer_t layer, const ncnn_option_t opt)
+{
+    return layer_of(layer)->ncnn::Layer::create_pipeline(*(const ncnn::Option*)opt);
+}
+
+static int layer_destroy_pipeline(ncnn_layer_t layer, const ncnn_option_t opt)
+{
+    return layer_of(layer)->ncnn::Layer::destroy_pipeline(*(const ncnn::Option*)opt);
+}
+
+static int layer_forward_1(const ncnn_layer_t layer, const ncnn_mat_t bottom_blob, ncnn_mat_t* top_blob, const ncnn_option_t opt)
+{
+    ncnn::Mat* top_blob0 = new ncnn::Mat;
+    int ret = layer_of(layer)->ncnn::Layer::forward(*(const ncnn::Mat*)bottom_blob, *top_blob0, *(const ncnn::Option*)opt);
+    *top_blob = (ncnn_mat_t)top_blob0;
+    return ret;
+}
+
+static int layer_forward_n(const ncnn_layer_t layer, const ncnn_mat_t* bottom_blobs, int n, ncnn_mat_t* top_blobs, int n2, const ncnn_option_t opt)
+{
+    std::vector<ncnn::Mat> bottom_blobs0(n);
+    for (int i = 0; i < n; i++)
+    {
+        bottom_blobs0[i] = *(const ncnn::Mat*)bottom_blobs[i];
+    }
+    std::vector<ncnn::Mat> top_blobs0(n2);
+    int ret = layer_of(layer)->ncnn::Layer::forward(bottom_blobs0, top_blobs0, *(const ncnn::Option*)opt);
+    for (int i = 0; i < n2; i++)
+    {
+        top_blobs[i] = (ncnn_mat_t)(new ncnn::Mat(top_blobs0[i]));
+    }
+    return ret;
+}
+
+static int layer_forward_inplace_1(const ncnn_layer_t layer, ncnn_mat_t bottom_top_blob, const ncnn_option_t opt)
+{
+    return layer_of(layer)->ncnn::Layer::forward_inplace(*(ncnn::Mat*)bottom_top_blob, *(const ncnn::Option*)opt);
+}
+
+static int layer_forward_inplace_n(const ncnn_layer_t layer, ncnn_mat_t* bottom_top_blobs, int n, const ncnn_option_t opt)
+{
+    std::vector<ncnn::Mat> bottom_top_blobs0(n);
+    for (int i = 0; i < n; i++)
+    {
+        bottom_top_blobs0[i] = *(ncnn::Mat*)bottom_top_blobs[i];
+    }
+    int ret = layer_of(layer)->ncnn::Layer::forward_inplace(bottom_top_blobs0, *(const ncnn::Option*)opt);
+    for (int i = 0; i < n; i++)
+    {
+        *(ncnn::Mat*)bottom_top_blobs[i] = bottom_top_blobs0[i];
+    }
+    return ret;
+}
+
+ncnn_layer_t ncnn_ext_layer_create_userdata(void* userdata)
+{
+    /* same layout as ncnn_layer_create, so ncnn_layer_destroy releases it */
+    ncnn_layer_t layer = (ncnn_layer_t)malloc(sizeof(struct __ncnn_layer_t));
+    layer->pthis = (void*)(new Layer_userdata(layer, userdata));
+    layer->load_param = layer_load_param;
+    layer->load_model = layer_load_model;
+    layer->create_pipeline = layer_create_pipeline;
+    layer->destroy_pipeline = layer_destroy_pipeline;
+    layer->forward_1 = layer_forward_1;
+    layer->forward_n = layer_forward_n;
+    layer->forward_inplace_1 = layer_forward_inplace_1;
+    layer->forward_inplace_n = layer_forward_inplace_n;
+    return layer;
+}
+
+void* ncnn_ext_layer_get_userdata(const ncnn_layer_t layer)
+{
+    return layer_of(layer)->ext_userdata;
+}
+
+ncnn_mat_t ncnn_ext_mat_share(const ncnn_mat_t mat)
+{
+    return (ncnn_mat_t)(new ncnn::Mat(*(const ncnn::Mat*)mat));
+}
+
+void ncnn_ext_mat_assign(ncnn_mat_t dst, const ncnn_mat_t src)
+{
+    *(ncnn::Mat*)dst = *(const ncnn::Mat*)src;
+}
+
+static const ncnn::Net* net_of(const ncnn_net_t net)
+{
+    return (const ncnn::Net*)net->pthis;
+}
+
+int ncnn_ext_net_get_layer_count(const ncnn_net_t net)
+{
+    return (int)net_of(net)->layers().size();
+}
+
+int ncnn_ext_net_get_blob_count(const ncnn_net_t net)
+{
+    return (int)net_of(net)->blobs().size();
+}
+
+static bool has_layer(const ncnn_net_t net, int i)
+{
+    return i >= 0 && i < (int)net_of(net)->layers().size();
+}
+
+static bool has_blob(const ncnn_net_t net, int i)
+{
+    return i >= 0 && i < (int)net_of(net)->blobs().size();
+}
+
+#if NCNN_STRING
+const char* ncnn_ext_net_get_layer_type(const ncnn_net_t net, int i)
+{
+    return has_layer(net, i) ? net_of(net)->layers()[i]->type.c_str() : 0;
+}
+
+const char* ncnn_ext_net_get_layer_name(const ncnn_net_t net, int i)
+{
+    return has_layer(net, i) ? net_of(net)->layers()[i]->name.c_str() : 0;
+}
+
+const char* ncnn_ext_net_get_blob_name(const ncnn_net_t net, int i)
+{
+    return has_blob(net, i) ? net_of(net)->blobs()[i].name.c_str() : 0;
+}
+
+const char* ncnn_ext_net_get_input_name(const ncnn_net_t net, int i)
+{
+    const std::vector<const char*>& names = net_of(net)->input_names();
+    return i >= 0 && i < (int)names.size() ? names[i] : 0;
+}
+
+const char* ncnn_ext_net_get_output_name(const ncnn_net_t net, int i)
+{
+    const std::vector<const char*>& names = net_of(net)->output_names();
+    return i >= 0 && i < (int)names.size() ? names[i] : 0;
+}
+#endif /* NCNN_STRING */
+
+int ncnn_ext_net_get_layer_bottom_count(const ncnn_net_t net, int i)
+{
+    return has_layer(net, i) ? (int)net_of(net)->layers()[i]->bottoms.size() : 0;
+}
+
+int ncnn_ext_net_get_layer_bottom(const ncnn_net_t net, int i, int j)
+{
+    if (!has_layer(net, i) || j < 0 || j >= (int)net_of(net)->layers()[i]->bottoms.size())
+        return -1;
+    return net_of(net)->layers()[i]->bottoms[j];
+}
+
+int ncnn_ext_net_get_layer_top_count(const ncnn_net_t net, int i)
+{
+    return has_layer(net, i) ? (int)net_of(net)->layers()[i]->tops.size() : 0;
+}
+
+int ncnn_ext_net_get_layer_top(const ncnn_net_t net, int i, int j)
+{
+    if (!has_layer(net, i) || j < 0 || j >= (int)net_of(net)->layers()[i]->tops.size())
+        return -1;
+    return net_of(net)->layers()[i]->tops[j];
+}
+
+void ncnn_ext_net_get_blob_shape(const ncnn_net_t net, int i, int* dims, int* w, int* h, int* c)
+{
+    if (!has_blob(net, i))
+    {
+        *dims = *w = *h = *c = 0;
+        return;
+    }
+    const ncnn::Mat& shape = net_of(net)->blobs()[i].shape;
+    *dims = shape.dims;
+    *w = shape.w;
+    *h = shape.h;
+    *c = shape.c;
+}
+
+#if NCNN_PIXEL
+void ncnn_ext_yuv420sp2rgb(const unsigned char* yuv420sp, int w, int h, unsigned char* rgb)
+{
+    ncnn::yuv420sp2rgb(yuv420sp, w, h, rgb);
+}
+
+void ncnn_ext_yuv420sp2rgb_nv12(const unsigned char* yuv420sp, int w, int h, unsigned char* rgb)
+{
+    ncnn::yuv420sp2rgb_nv12(yuv420sp, w, h, rgb);
+}
+#endif /* NCNN_PIXEL */
+
+#if NCNN_PIXEL_ROTATE
+void ncnn_ext_kanna_rotate(const unsigned char* src, int srcw, int srch, int srcstride, unsigned char* dst, int w, int h, int stride, int channels, int type)
+{
+    switch (channels)
+    {
+    case 1:
+        ncnn::kanna_rotate_c1(src, srcw, srch, srcstride, dst, w, h, stride, type);
+        break;
+    case 2:
+        ncnn::kanna_rotate_c2(src, srcw, srch, srcstride, dst, w, h, stride, type);
+        break;
+    case 3:
+        ncnn::kanna_rotate_c3(src, srcw, srch, srcstride, dst, w, h, stride, type);
+        break;
+    case 4:
+        ncnn::kanna_rotate_c4(src, srcw, srch, srcstride, dst, w, h, stride, type);
+        break;
+    }
+}
+#endif /* NCNN_PIXEL_ROTATE */
+
+#if NCNN_PIXEL_AFFINE
+void ncnn_ext_get_affine_transform(const float* points_from, const float* points_to, int num_point, float* tm)
+{
+    ncnn::get_affine_transform(points_from, points_to, num_point, tm);
+}
+
+void ncnn_ext_invert_affine_transform(const float* tm, float* tm_inv)
+{
+    ncnn::invert_affine_transform(tm, tm_inv);
+}
+
+void ncnn_ext_warpaffine_bilinear(const unsigned char* src, int srcw, int srch, int srcstride, unsigned char* dst, int w, int h, int stride, int channels, const float* tm, int type, unsigned int v)
+{
+    switch (channels)
+    {
+    case 1:
+        ncnn::warpaffine_bilinear_c1(src, srcw, srch, srcstride, dst, w, h, stride, tm, type, v);
+        break;
+    case 2:
+        ncnn::warpaffine_bilinear_c2(src, srcw, srch, srcstride, dst, w, h, stride, tm, type, v);
+        break;
+    case 3:
+        ncnn::warpaffine_bilinear_c3(src, srcw, srch, srcstride, dst, w, h, stride, tm, type, v);
+        break;
+    case 4:
+        ncnn::warpaffine_bilinear_c4(src, srcw, srch, srcstride, dst, w, h, stride, tm, type, v);
+        break;
+    }
+}
+#endif /* NCNN_PIXEL_AFFINE */
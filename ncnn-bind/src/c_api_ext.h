@@ -0,0 +1,82 @@
+/* C wrappers of ncnn C++ functions which are not part of c_api.h */
+
+#ifndef NCNN_RS_C_API_EXT_H
+#define NCNN_RS_C_API_EXT_H
+
+#include "c_api.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* datareader calling back with userdata, destroy with ncnn_datareader_destroy */
+typedef int (*ncnn_ext_datareader_scan_t)(void* userdata, const char* format, void* p);
+typedef size_t (*ncnn_ext_datareader_read_t)(void* userdata, void* buf, size_t size);
+NCNN_EXPORT ncnn_datareader_t ncnn_ext_datareader_create_userdata(ncnn_ext_datareader_scan_t scan, ncnn_ext_datareader_read_t read, void* userdata);
+
+/* datareader over size bytes of mem, reads past the end fail; aligned weights are
+ * referenced in place, so mem must outlive the net, destroy with ncnn_datareader_destroy */
+NCNN_EXPORT ncnn_datareader_t ncnn_ext_datareader_create_from_memory(const unsigned char* mem, size_t size);
+
+#if NCNN_STRING
+/* replaces all params of pd by the ones in text, in .param notation, e.g. "0=64 -23302=2,3,5";
+ * unlike ncnn_paramdict_set_array, arrays keep their int or float element type */
+NCNN_EXPORT int ncnn_ext_paramdict_load_param(ncnn_paramdict_t pd, const char* text);
+#endif /* NCNN_STRING */
+
+/* layer keeping userdata next to the vtable, destroy with ncnn_layer_destroy;
+ * vtable entries default to the ncnn::Layer implementation and may be replaced */
+NCNN_EXPORT ncnn_layer_t ncnn_ext_layer_create_userdata(void* userdata);
+NCNN_EXPORT void* ncnn_ext_layer_get_userdata(const ncnn_layer_t layer);
+
+/* modelbin serving one weight per load, empty past the end or when the weight size
+ * differs from the requested one, destroy with ncnn_modelbin_destroy */
+NCNN_EXPORT ncnn_modelbin_t ncnn_ext_modelbin_create_from_mat_array(const ncnn_mat_t* weights, int n);
+
+/* new header sharing data with mat, destroy with ncnn_mat_destroy */
+NCNN_EXPORT ncnn_mat_t ncnn_ext_mat_share(const ncnn_mat_t mat);
+/* makes dst share data with src */
+NCNN_EXPORT void ncnn_ext_mat_assign(ncnn_mat_t dst, const ncnn_mat_t src);
+
+/* net layer and blob introspection, indexes follow the param order;
+ * out of range indexes give null names, -1 blobs and zero counts */
+NCNN_EXPORT int ncnn_ext_net_get_layer_count(const ncnn_net_t net);
+NCNN_EXPORT int ncnn_ext_net_get_blob_count(const ncnn_net_t net);
+#if NCNN_STRING
+NCNN_EXPORT const char* ncnn_ext_net_get_layer_type(const ncnn_net_t net, int i);
+NCNN_EXPORT const char* ncnn_ext_net_get_layer_name(const ncnn_net_t net, int i);
+NCNN_EXPORT const char* ncnn_ext_net_get_blob_name(const ncnn_net_t net, int i);
+/* unlike ncnn_net_get_input_name, null when i is out of range, e.g. after binary params */
+NCNN_EXPORT const char* ncnn_ext_net_get_input_name(const ncnn_net_t net, int i);
+NCNN_EXPORT const char* ncnn_ext_net_get_output_name(const ncnn_net_t net, int i);
+#endif /* NCNN_STRING */
+NCNN_EXPORT int ncnn_ext_net_get_layer_bottom_count(const ncnn_net_t net, int i);
+NCNN_EXPORT int ncnn_ext_net_get_layer_bottom(const ncnn_net_t net, int i, int j);
+NCNN_EXPORT int ncnn_ext_net_get_layer_top_count(const ncnn_net_t net, int i);
+NCNN_EXPORT int ncnn_ext_net_get_layer_top(const ncnn_net_t net, int i, int j);
+/* blob shape from -23330 hints, dims is 0 when the param carries no hint */
+NCNN_EXPORT void ncnn_ext_net_get_blob_shape(const ncnn_net_t net, int i, int* dims, int* w, int* h, int* c);
+
+#if NCNN_PIXEL
+/* yuv420sp pixel api, w and h must be even */
+NCNN_EXPORT void ncnn_ext_yuv420sp2rgb(const unsigned char* yuv420sp, int w, int h, unsigned char* rgb);
+NCNN_EXPORT void ncnn_ext_yuv420sp2rgb_nv12(const unsigned char* yuv420sp, int w, int h, unsigned char* rgb);
+#endif /* NCNN_PIXEL */
+
+#if NCNN_PIXEL_ROTATE
+/* type is the exif orientation of src, 1~8, dst is upright; channels is 1~4 */
+NCNN_EXPORT void ncnn_ext_kanna_rotate(const unsigned char* src, int srcw, int srch, int srcstride, unsigned char* dst, int w, int h, int stride, int channels, int type);
+#endif /* NCNN_PIXEL_ROTATE */
+
+#if NCNN_PIXEL_AFFINE
+NCNN_EXPORT void ncnn_ext_get_affine_transform(const float* points_from, const float* points_to, int num_point, float* tm);
+NCNN_EXPORT void ncnn_ext_invert_affine_transform(const float* tm, float* tm_inv);
+/* type is border type, 0 for constant v and -233 for transparent; channels is 1~4 */
+NCNN_EXPORT void ncnn_ext_warpaffine_bilinear(const unsigned char* src, int srcw, int srch, int srcstride, unsigned char* dst, int w, int h, int stride, int channels, const float* tm, int type, unsigned int v);
+#endif /* NCNN_PIXEL_AFFINE */
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* NCNN_RS_C_API_EXT_H */
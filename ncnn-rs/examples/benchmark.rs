@@ -20,14 +20,16 @@ fn benchmark(name: &str, mut mat_in: Mat, opt: &ncnn_option, out: &str) -> anyho
     }
 
     net.set_option(opt);
-    net.load_param(path.to_str().unwrap())?;
+    net.load_param(&path)?;
     let dr = DataReader::empty();
     net.load_model_datareader(&dr)?;
 
     // warmup
-    let mut ex_warmup = net.create_extractor();
-    ex_warmup.input("data", &mat_in)?;
-    ex_warmup.extract(out, &mut mat_out)?;
+    {
+        let mut ex_warmup = net.create_extractor();
+        ex_warmup.input("data", &mat_in)?;
+        ex_warmup.extract(out, &mut mat_out)?;
+    }
 
     let loop_cnt = 10;
     let now = time::Instant::now();
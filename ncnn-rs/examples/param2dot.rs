@@ -0,0 +1,11 @@
+use ncnn_rs::param::Graph;
+
+// Usage: cargo run --example param2dot -- model.param | dot -Tsvg > model.svg
+fn main() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("usage: param2dot <model.param>"))?;
+    let graph = Graph::from_file(path)?;
+    print!("{}", graph.to_dot());
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use crate::mat::Mat;
+use crate::modelbin::ModelBin;
+use crate::option::Option as NcnnOption;
+use crate::paramdict::ParamDict;
+use ncnn_bind::*;
+use std::any::Any;
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+use std::os::raw::{c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Layer implemented in Rust, see [Net::register_custom_layer](crate::Net::register_custom_layer).
+///
+/// Every layer of the registered type in a param gets its own `Default` instance. Forward
+/// methods take `&self` as several extractors may run the layer concurrently.
+///
+/// Which forward method ncnn calls depends on [CustomLayer::ONE_BLOB_ONLY] and
+/// [CustomLayer::SUPPORT_INPLACE], the others are never called.
+///
+/// Errors and panics of a layer are returned by the [Net](crate::Net) load or
+/// [Extractor](crate::Extractor) extract call which ran it.
+pub trait CustomLayer: Default + Send + Sync + 'static {
+    /// Layer has exactly one input and one output blob.
+    const ONE_BLOB_ONLY: bool = true;
+    /// Layer writes its outputs over its inputs.
+    const SUPPORT_INPLACE: bool = false;
+
+    /// Reads layer params of the `.param` line.
+    fn load_param(&mut self, _pd: &ParamDict) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Reads layer weights.
+    fn load_model(&mut self, _mb: &ModelBin) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Computes `top` from `bottom`, for one-blob layers.
+    fn forward(&self, _bottom: &Mat, _top: &mut Mat, _opt: &NcnnOption) -> anyhow::Result<()> {
+        anyhow::bail!("forward is not implemented")
+    }
+
+    /// Computes `tops` from `bottoms`, `tops` holds an empty matrix per output blob.
+    fn forward_n(
+        &self,
+        _bottoms: &[Mat],
+        _tops: &mut [Mat],
+        _opt: &NcnnOption,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("forward_n is not implemented")
+    }
+
+    /// Overwrites `blob` with the output, for one-blob in-place layers.
+    fn forward_inplace(&self, _blob: &mut Mat, _opt: &NcnnOption) -> anyhow::Result<()> {
+        anyhow::bail!("forward_inplace is not implemented")
+    }
+
+    /// Overwrites `blobs` with the outputs, for in-place layers.
+    fn forward_inplace_n(&self, _blobs: &mut [Mat], _opt: &NcnnOption) -> anyhow::Result<()> {
+        anyhow::bail!("forward_inplace_n is not implemented")
+    }
+}
+
+pub(crate) unsafe extern "C" fn creator<L: CustomLayer>(_userdata: *mut c_void) -> ncnn_layer_t {
+    // ncnn has no way to fail layer creation.
+    let state = catch_unwind(L::default).unwrap_or_else(|_| std::process::abort());
+    let layer = ncnn_ext_layer_create_userdata(Box::into_raw(Box::new(state)) as *mut c_void);
+
+    ncnn_layer_set_one_blob_only(layer, L::ONE_BLOB_ONLY as c_int);
+    ncnn_layer_set_support_inplace(layer, L::SUPPORT_INPLACE as c_int);
+    (*layer).load_param = Some(load_param::<L>);
+    (*layer).load_model = Some(load_model::<L>);
+    match (L::ONE_BLOB_ONLY, L::SUPPORT_INPLACE) {
+        (true, false) => (*layer).forward_1 = Some(forward_1::<L>),
+        (false, false) => (*layer).forward_n = Some(forward_n::<L>),
+        (true, true) => (*layer).forward_inplace_1 = Some(forward_inplace_1::<L>),
+        (false, true) => (*layer).forward_inplace_n = Some(forward_inplace_n::<L>),
+    }
+    layer
+}
+
+pub(crate) unsafe extern "C" fn destroyer<L: CustomLayer>(
+    layer: ncnn_layer_t,
+    _userdata: *mut c_void,
+) {
+    let state = state::<L>(layer);
+    ncnn_layer_destroy(layer);
+    drop(Box::from_raw(state));
+}
+
+unsafe fn state<L: CustomLayer>(layer: ncnn_layer_t) -> *mut L {
+    ncnn_ext_layer_get_userdata(layer) as *mut L
+}
+
+thread_local! {
+    // ncnn only reports that a layer failed, so the cause is kept aside for run_ncnn.
+    static LAST_ERROR: RefCell<Option<anyhow::Error>> = const { RefCell::new(None) };
+}
+
+/// Runs an ncnn call which may run custom layers on this thread. A failure is reported as
+/// `context`, caused by the error of the custom layer that failed during the call, if any.
+pub(crate) fn run_ncnn(f: impl FnOnce() -> c_int, context: String) -> anyhow::Result<()> {
+    LAST_ERROR.with(|e| e.borrow_mut().take());
+    let ret = f();
+    let cause = LAST_ERROR.with(|e| e.borrow_mut().take());
+    match (ret, cause) {
+        (0, _) => Ok(()),
+        (_, Some(cause)) => Err(cause.context(context)),
+        (_, None) => Err(anyhow::anyhow!(context)),
+    }
+}
+
+/// Converts a layer result to an ncnn status, keeping the error for [run_ncnn].
+fn status(what: &str, result: std::thread::Result<anyhow::Result<()>>) -> c_int {
+    let error = match result {
+        Ok(Ok(())) => return 0,
+        Ok(Err(e)) => e.context(format!("Custom layer {} failed", what)),
+        Err(panic) => anyhow::anyhow!(
+            "Custom layer {} panicked: {}",
+            what,
+            panic_message(panic.as_ref())
+        ),
+    };
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(error));
+    -1
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+unsafe fn mats<'a>(ptr: *const ncnn_mat_t, n: c_int) -> &'a [ncnn_mat_t] {
+    if n <= 0 || ptr.is_null() {
+        return &[];
+    }
+    std::slice::from_raw_parts(ptr, n as usize)
+}
+
+unsafe extern "C" fn load_param<L: CustomLayer>(
+    layer: ncnn_layer_t,
+    pd: ncnn_paramdict_t,
+) -> c_int {
+    let state = &mut *state::<L>(layer);
+    let pd = ManuallyDrop::new(ParamDict::from_ptr(pd));
+    status(
+        "load_param",
+        catch_unwind(AssertUnwindSafe(|| state.load_param(&pd))),
+    )
+}
+
+unsafe extern "C" fn load_model<L: CustomLayer>(layer: ncnn_layer_t, mb: ncnn_modelbin_t) -> c_int {
+    let state = &mut *state::<L>(layer);
+    let mb = ManuallyDrop::new(ModelBin::from_ptr(mb));
+    status(
+        "load_model",
+        catch_unwind(AssertUnwindSafe(|| state.load_model(&mb))),
+    )
+}
+
+unsafe extern "C" fn forward_1<L: CustomLayer>(
+    layer: ncnn_layer_t,
+    bottom_blob: ncnn_mat_t,
+    top_blob: *mut ncnn_mat_t,
+    opt: ncnn_option_t,
+) -> c_int {
+    let state = &*state::<L>(layer);
+    let opt = ManuallyDrop::new(NcnnOption::from_ptr(opt));
+    let bottom = Mat::share(bottom_blob);
+    let mut top = Mat::new();
+    let ret = status(
+        "forward",
+        catch_unwind(AssertUnwindSafe(|| state.forward(&bottom, &mut top, &opt))),
+    );
+    *top_blob = top.into_raw();
+    ret
+}
+
+unsafe extern "C" fn forward_n<L: CustomLayer>(
+    layer: ncnn_layer_t,
+    bottom_blobs: *const ncnn_mat_t,
+    n: c_int,
+    top_blobs: *mut ncnn_mat_t,
+    n2: c_int,
+    opt: ncnn_option_t,
+) -> c_int {
+    let state = &*state::<L>(layer);
+    let opt = ManuallyDrop::new(NcnnOption::from_ptr(opt));
+    let bottoms = mats(bottom_blobs, n)
+        .iter()
+        .map(|m| Mat::share(*m))
+        .collect::<Vec<_>>();
+    let mut tops = (0..n2).map(|_| Mat::new()).collect::<Vec<_>>();
+    let ret = status(
+        "forward",
+        catch_unwind(AssertUnwindSafe(|| {
+            state.forward_n(&bottoms, &mut tops, &opt)
+        })),
+    );
+    for (i, top) in tops.into_iter().enumerate() {
+        *top_blobs.add(i) = top.into_raw();
+    }
+    ret
+}
+
+unsafe extern "C" fn forward_inplace_1<L: CustomLayer>(
+    layer: ncnn_layer_t,
+    bottom_top_blob: ncnn_mat_t,
+    opt: ncnn_option_t,
+) -> c_int {
+    let state = &*state::<L>(layer);
+    let opt = ManuallyDrop::new(NcnnOption::from_ptr(opt));
+    let mut blob = Mat::share(bottom_top_blob);
+    let ret = status(
+        "forward",
+        catch_unwind(AssertUnwindSafe(|| state.forward_inplace(&mut blob, &opt))),
+    );
+    write_back(bottom_top_blob, blob);
+    ret
+}
+
+unsafe extern "C" fn forward_inplace_n<L: CustomLayer>(
+    layer: ncnn_layer_t,
+    bottom_top_blobs: *mut ncnn_mat_t,
+    n: c_int,
+    opt: ncnn_option_t,
+) -> c_int {
+    let state = &*state::<L>(layer);
+    let opt = ManuallyDrop::new(NcnnOption::from_ptr(opt));
+    let targets = mats(bottom_top_blobs, n);
+    let mut blobs = targets.iter().map(|m| Mat::share(*m)).collect::<Vec<_>>();
+    let ret = status(
+        "forward",
+        catch_unwind(AssertUnwindSafe(|| {
+            state.forward_inplace_n(&mut blobs, &opt)
+        })),
+    );
+    for (target, blob) in targets.iter().zip(blobs) {
+        write_back(*target, blob);
+    }
+    ret
+}
+
+/// Layers may replace an in-place blob instead of writing into it.
+unsafe fn write_back(target: ncnn_mat_t, mat: Mat) {
+    let mat = mat.into_raw();
+    ncnn_ext_mat_assign(target, mat);
+    ncnn_mat_destroy(mat);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CustomLayer, DataReader, Mat, ModelBin, Net, ParamDict};
+
+    #[derive(Default)]
+    struct Scale {
+        factor: f32,
+    }
+
+    impl CustomLayer for Scale {
+        const SUPPORT_INPLACE: bool = true;
+
+        fn load_param(&mut self, pd: &ParamDict) -> anyhow::Result<()> {
+            self.factor = pd.get_float(0, 1.0);
+            Ok(())
+        }
+
+        fn forward_inplace(
+            &self,
+            blob: &mut Mat,
+            _opt: &crate::option::Option,
+        ) -> anyhow::Result<()> {
+            for c in 0..blob.c() {
+                blob.channel_mut::<f32>(c)?
+                    .iter_mut()
+                    .for_each(|v| *v *= self.factor);
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Concat;
+
+    impl CustomLayer for Concat {
+        const ONE_BLOB_ONLY: bool = false;
+
+        fn forward_n(
+            &self,
+            bottoms: &[Mat],
+            tops: &mut [Mat],
+            _opt: &crate::option::Option,
+        ) -> anyhow::Result<()> {
+            let mut data = Vec::new();
+            for bottom in bottoms {
+                data.extend_from_slice(bottom.as_slice::<f32>()?);
+            }
+            let w = data.len() as i32;
+            tops[0] = Mat::from_vec_1d(data, w)?;
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct AddBias {
+        bias: f32,
+    }
+
+    impl CustomLayer for AddBias {
+        fn load_model(&mut self, mb: &ModelBin) -> anyhow::Result<()> {
+            self.bias = mb.load_1d(1, 1)?.as_slice::<f32>()?[0];
+            Ok(())
+        }
+
+        fn forward(
+            &self,
+            bottom: &Mat,
+            top: &mut Mat,
+            _opt: &crate::option::Option,
+        ) -> anyhow::Result<()> {
+            let data = bottom
+                .as_slice::<f32>()?
+                .iter()
+                .map(|v| v + self.bias)
+                .collect::<Vec<_>>();
+            let w = data.len() as i32;
+            *top = Mat::from_vec_1d(data, w)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn run_custom_layers() {
+        let mut net = Net::new();
+        net.register_custom_layer::<Scale>("Scale2").unwrap();
+        net.register_custom_layer::<Concat>("Concat2").unwrap();
+        net.load_param_memory(
+            "7767517\n4 5\n\
+             Input data 0 1 data\n\
+             Split split 1 2 data a b\n\
+             Scale2 scale 1 1 a scaled 0=3.0\n\
+             Concat2 concat 2 1 scaled b out\n",
+        )
+        .unwrap();
+        net.load_model_datareader(&DataReader::empty()).unwrap();
+
+        let mut input = Mat::new_1d(2, None);
+        input.fill(1.0);
+        let mut output = Mat::new();
+        let mut ex = net.create_extractor();
+        ex.input("data", &input).unwrap();
+        ex.extract("out", &mut output).unwrap();
+        assert_eq!(&[3.0, 3.0, 1.0, 1.0], output.as_slice::<f32>().unwrap());
+    }
+
+    #[test]
+    fn load_custom_layer_weights() {
+        let mut net = Net::new();
+        net.register_custom_layer::<AddBias>("AddBias").unwrap();
+        net.load_param_memory("7767517\n2 2\nInput data 0 1 data\nAddBias bias 1 1 data out\n")
+            .unwrap();
+        net.load_model_memory(0.5f32.to_ne_bytes()).unwrap();
+
+        let input = Mat::from_vec_1d(vec![1.0, 2.0], 2).unwrap();
+        let mut output = Mat::new();
+        let mut ex = net.create_extractor();
+        ex.input("data", &input).unwrap();
+        ex.extract("out", &mut output).unwrap();
+        assert_eq!(&[1.5, 2.5], output.as_slice::<f32>().unwrap());
+    }
+
+    #[derive(Default)]
+    struct Unimplemented;
+
+    impl CustomLayer for Unimplemented {}
+
+    #[test]
+    fn custom_layer_errors_reach_extract() {
+        let mut net = Net::new();
+        net.register_custom_layer::<Unimplemented>("Unimplemented")
+            .unwrap();
+        net.load_param_memory("7767517\n2 2\nInput data 0 1 data\nUnimplemented u 1 1 data out\n")
+            .unwrap();
+        net.load_model_datareader(&DataReader::empty()).unwrap();
+
+        let input = Mat::from_vec_1d(vec![1.0], 1).unwrap();
+        let mut output = Mat::new();
+        let mut ex = net.create_extractor();
+        ex.input("data", &input).unwrap();
+        let error = ex.extract("out", &mut output).unwrap_err();
+        assert!(format!("{:#}", error).contains("forward is not implemented"));
+    }
+
+    #[test]
+    fn register_rejects_nul() {
+        let mut net = Net::new();
+        assert!(net.register_custom_layer::<Scale>("Scale\0").is_err());
+    }
+}
@@ -1,5 +1,11 @@
 use libc::memset;
 use ncnn_bind::*;
+use std::ffi::CStr;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr::NonNull;
 
 pub type ScanFn = unsafe extern "C" fn(
     dr: ncnn_datareader_t,
@@ -30,31 +36,106 @@ unsafe extern "C" fn empty_read(
     size
 }
 
-pub struct DataReader {
+/// Value parsed by a single scanf conversion, see [ModelSource::scan].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScanValue {
+    /// Result of `%d`.
+    Int(i32),
+    /// Result of `%f`.
+    Float(f32),
+    /// Result of `%s` or `%[...]`, truncated to the conversion width by the caller.
+    Str(String),
+}
+
+/// Stateful source of params and weights, see [DataReader::from_source].
+pub trait ModelSource {
+    /// Reads up to `buf.len()` bytes and returns the number of bytes read.
+    ///
+    /// ncnn treats reading fewer bytes than requested as an error.
+    fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    /// Parses a single value described by a scanf-style `format`, used by text params only.
+    ///
+    /// ncnn asks for `%d`, `%d=`, `%15s`, `%255s` and `,%15[^,\n ]`. Returns `None`
+    /// when the input does not match, the default never matches.
+    fn scan(&mut self, format: &str) -> Option<ScanValue> {
+        let _ = format;
+        None
+    }
+}
+
+pub struct DataReader<'a> {
     ptr: ncnn_datareader_t,
+    // Called back through userdata, must outlive ptr.
+    source: Option<NonNull<dyn ModelSource + 'a>>,
+    _phantom: PhantomData<Box<dyn ModelSource + 'a>>,
 }
 
-impl DataReader {
+impl DataReader<'static> {
     /// Creates an new [DataReader].
     ///
     /// # Safety
     ///
     /// Must not be used until scan and read functions are set.
     pub unsafe fn new() -> Self {
-        Self {
-            ptr: ncnn_datareader_create(),
-        }
+        Self::from_ptr(ncnn_datareader_create())
     }
 
     /// Creates an empty [DataReader] that always reads zero bytes.
     pub fn empty() -> Self {
+        Self::from_ptr(unsafe {
+            let ptr = ncnn_datareader_create();
+            (*ptr).scan = Some(empty_scan);
+            (*ptr).read = Some(empty_read);
+            ptr
+        })
+    }
+
+    fn from_ptr(ptr: ncnn_datareader_t) -> Self {
+        Self {
+            ptr,
+            source: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> DataReader<'a> {
+    /// Creates a [DataReader] streaming params or weights from `reader`.
+    ///
+    /// Text params are parsed from the stream as well, as with `fscanf`.
+    pub fn from_read<R: Read + 'a>(reader: R) -> Self {
+        Self::from_source(ReadSource::new(reader))
+    }
+
+    /// Creates a [DataReader] calling back into `source`.
+    ///
+    /// Panics in `source` are caught before reaching ncnn and reported as failed reads.
+    pub fn from_source<S: ModelSource + 'a>(source: S) -> Self {
+        let source = Box::into_raw(Box::new(source));
+        Self {
+            ptr: unsafe {
+                ncnn_ext_datareader_create_userdata(
+                    Some(scan_trampoline::<S>),
+                    Some(read_trampoline::<S>),
+                    source as *mut c_void,
+                )
+            },
+            source: NonNull::new(source as *mut (dyn ModelSource + 'a)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a [DataReader] over `mem`, reads past its end fail.
+    ///
+    /// Loading weights references aligned data in place, so `mem` must outlive the net.
+    pub(crate) fn from_memory(mem: &'a [u8]) -> Self {
         Self {
             ptr: unsafe {
-                let ptr = ncnn_datareader_create();
-                (*ptr).scan = Some(empty_scan);
-                (*ptr).read = Some(empty_read);
-                ptr
+                ncnn_ext_datareader_create_from_memory(mem.as_ptr(), mem.len() as size_t)
             },
+            source: None,
+            _phantom: PhantomData,
         }
     }
 
@@ -71,19 +152,265 @@ impl DataReader {
     }
 }
 
-impl Drop for DataReader {
+impl<'a> Drop for DataReader<'a> {
     fn drop(&mut self) {
         unsafe {
             ncnn_datareader_destroy(self.ptr);
+            if let Some(source) = self.source {
+                drop(Box::from_raw(source.as_ptr()));
+            }
+        }
+    }
+}
+
+/// Conversion of a scanf format, ncnn passes one target pointer per call.
+struct Conversion {
+    kind: u8,
+    width: Option<usize>,
+}
+
+impl Conversion {
+    fn parse(format: &str) -> Option<Conversion> {
+        let spec = format.split('%').nth(1)?;
+        let digits = spec.bytes().take_while(u8::is_ascii_digit).count();
+        Some(Conversion {
+            kind: *spec.as_bytes().get(digits)?,
+            width: spec[..digits].parse().ok(),
+        })
+    }
+}
+
+/// Buffered [Read] implementing the scanf subset ncnn uses to parse text params,
+/// i.e. `%d`, `%f`, `%Ns`, `%N[...]` and literal characters.
+struct ReadSource<R> {
+    reader: BufReader<R>,
+}
+
+impl<R: Read> ModelSource for ReadSource<R> {
+    /// Fills `buf` unless the stream ends or fails.
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+        filled
+    }
+
+    fn scan(&mut self, format: &str) -> Option<ScanValue> {
+        let mut value = None;
+        let mut format = format.bytes().peekable();
+        while let Some(f) = format.next() {
+            if f.is_ascii_whitespace() {
+                self.skip_whitespace();
+            } else if f != b'%' || format.peek() == Some(&b'%') {
+                if f == b'%' {
+                    format.next();
+                }
+                if self.peek()? != f {
+                    return value;
+                }
+                self.reader.consume(1);
+            } else {
+                let mut width = 0;
+                while let Some(d) = format.next_if(u8::is_ascii_digit) {
+                    width = width * 10 + (d - b'0') as usize;
+                }
+                let width = if width == 0 { usize::MAX } else { width };
+                let kind = format.next()?;
+                if kind != b'[' {
+                    self.skip_whitespace();
+                }
+                value = Some(match kind {
+                    b'd' => ScanValue::Int(self.scan_number(width, false).parse().ok()?),
+                    b'f' => ScanValue::Float(self.scan_number(width, true).parse().ok()?),
+                    b's' => ScanValue::Str(self.scan_while(width, |c| !c.is_ascii_whitespace())?),
+                    b'[' => {
+                        let negate = format.next_if_eq(&b'^').is_some();
+                        let mut set = Vec::new();
+                        if let Some(c) = format.next_if_eq(&b']') {
+                            set.push(c);
+                        }
+                        for c in format.by_ref() {
+                            if c == b']' {
+                                break;
+                            }
+                            set.push(c);
+                        }
+                        ScanValue::Str(self.scan_while(width, |c| set.contains(&c) != negate)?)
+                    }
+                    _ => return None,
+                });
+            }
+        }
+        value
+    }
+}
+
+impl<R: Read> ReadSource<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.reader.fill_buf().ok()?.first().copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_whitespace()) {
+            self.reader.consume(1);
+        }
+    }
+
+    fn scan_while(&mut self, width: usize, accept: impl Fn(u8) -> bool) -> Option<String> {
+        let mut bytes = Vec::new();
+        while bytes.len() < width {
+            match self.peek() {
+                Some(c) if accept(c) => bytes.push(c),
+                _ => break,
+            }
+            self.reader.consume(1);
+        }
+        if bytes.is_empty() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    }
+
+    fn scan_number(&mut self, width: usize, float: bool) -> String {
+        let mut number = String::new();
+        let mut prev = None;
+        while number.len() < width {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => break,
+            };
+            let accept = c.is_ascii_digit()
+                || ((c == b'-' || c == b'+')
+                    && (prev.is_none() || matches!(prev, Some(b'e' | b'E'))))
+                || (float && (c == b'.' || c == b'e' || c == b'E'));
+            if !accept {
+                break;
+            }
+            number.push(c as char);
+            prev = Some(c);
+            self.reader.consume(1);
+        }
+        number
+    }
+}
+
+unsafe extern "C" fn scan_trampoline<S: ModelSource>(
+    userdata: *mut c_void,
+    format: *const c_char,
+    p: *mut c_void,
+) -> c_int {
+    let source = &mut *(userdata as *mut S);
+    let format = match CStr::from_ptr(format).to_str() {
+        Ok(format) => format,
+        Err(_) => return 0,
+    };
+    let conversion = match Conversion::parse(format) {
+        Some(conversion) => conversion,
+        None => return 0,
+    };
+
+    let value = catch_unwind(AssertUnwindSafe(|| source.scan(format)));
+    match (value, conversion.kind) {
+        (Ok(Some(ScanValue::Int(v))), b'd') => *(p as *mut c_int) = v,
+        (Ok(Some(ScanValue::Float(v))), b'f') => *(p as *mut f32) = v,
+        (Ok(Some(ScanValue::Str(v))), b's' | b'[') => {
+            // Target buffer holds width characters and a nul terminator.
+            let width = match conversion.width {
+                Some(width) => width,
+                None => return 0,
+            };
+            let len = v.len().min(width);
+            std::ptr::copy_nonoverlapping(v.as_ptr(), p as *mut u8, len);
+            *(p as *mut u8).add(len) = 0;
         }
+        _ => return 0,
     }
+    1
+}
+
+unsafe extern "C" fn read_trampoline<S: ModelSource>(
+    userdata: *mut c_void,
+    buf: *mut c_void,
+    size: size_t,
+) -> size_t {
+    let source = &mut *(userdata as *mut S);
+    let buf = std::slice::from_raw_parts_mut(buf as *mut u8, size as usize);
+    catch_unwind(AssertUnwindSafe(|| source.read(buf))).unwrap_or(0) as size_t
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{read_trampoline, ReadSource};
+    use crate::{ModelSource, ScanValue};
+
     #[test]
     fn empty_datareader() {
         use crate::datareader::*;
         let _ = DataReader::empty();
     }
+
+    #[test]
+    fn scan_param_tokens() {
+        let param = "7767517\nConvolution conv1 1 1 data conv1 0=64 -23300=2,1.5,-2e1\nReLU";
+        let mut source = ReadSource::new(param.as_bytes());
+        let mut scan = |format| match source.scan(format) {
+            Some(ScanValue::Int(v)) => format!("{}", v),
+            Some(ScanValue::Float(v)) => format!("{:?}", v),
+            Some(ScanValue::Str(v)) => v,
+            None => "-".to_string(),
+        };
+
+        assert_eq!("7767517", scan("%d"));
+        assert_eq!("Convolution", scan("%255s"));
+        assert_eq!("conv1", scan("%255s"));
+        assert_eq!(["1", "1"], [scan("%d"), scan("%d")]);
+        assert_eq!(["data", "conv1"], [scan("%255s"), scan("%255s")]);
+        assert_eq!(["0", "64"], [scan("%d="), scan("%15s")]);
+        assert_eq!(["-23300", "2"], [scan("%d="), scan("%d")]);
+        assert_eq!("1.5", scan(",%15[^,\n ]"));
+        assert_eq!("-2e1", scan(",%15[^,\n ]"));
+        assert_eq!("-", scan("%d="));
+        assert_eq!("ReLU", scan("%255s"));
+        assert_eq!("-", scan("%255s"));
+    }
+
+    struct Counter(u8);
+
+    impl ModelSource for Counter {
+        fn read(&mut self, buf: &mut [u8]) -> usize {
+            if self.0 == 0 {
+                panic!("Source exhausted");
+            }
+            for b in buf.iter_mut() {
+                self.0 -= 1;
+                *b = self.0;
+            }
+            buf.len()
+        }
+    }
+
+    #[test]
+    fn source_panics_are_caught() {
+        let mut source = Counter(3);
+        let userdata = &mut source as *mut Counter as *mut _;
+        let mut buf = [0u8; 3];
+        let read = unsafe { read_trampoline::<Counter>(userdata, buf.as_mut_ptr() as _, 3) };
+        assert_eq!((3, [2, 1, 0]), (read, buf));
+        let read = unsafe { read_trampoline::<Counter>(userdata, buf.as_mut_ptr() as _, 3) };
+        assert_eq!(0, read);
+        assert_eq!(None, source.scan("%d"));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::custom_layer;
 use ncnn_bind::*;
 use std::{ffi::CString, marker::PhantomData};
 
@@ -29,15 +30,61 @@ impl<'a> Extractor<'a> {
         }
     }
 
-    /// Runs network inferrence and returns output tensor by a given name.
-    pub fn extract(self, name: &str, mat: &mut crate::mat::Mat) -> anyhow::Result<()> {
-        let c_str = CString::new(name).unwrap();
-        if unsafe { ncnn_extractor_extract(self.ptr, c_str.as_ptr(), mat.mut_ptr()) } != 0 {
-            anyhow::bail!("Error running extract on layer `{}`", name);
+    /// Sets input tensor by a given blob index, see [ParamIds](crate::ParamIds).
+    pub fn input_index(&mut self, index: i32, mat: &'a crate::mat::Mat) -> anyhow::Result<()> {
+        if unsafe { ncnn_extractor_input_index(self.ptr, index, mat.ptr()) } != 0 {
+            anyhow::bail!("Error setting input for blob {}", index);
         } else {
             Ok(())
         }
     }
+
+    /// Runs network inferrence and returns output tensor by a given name.
+    ///
+    /// Computed blobs are kept, further extracts reuse them instead of recomputing.
+    pub fn extract(&mut self, name: &str, mat: &mut crate::mat::Mat) -> anyhow::Result<()> {
+        let c_str = CString::new(name)?;
+        let extract = || {
+            let mut out = std::ptr::null_mut();
+            let ret = unsafe { ncnn_extractor_extract(self.ptr, c_str.as_ptr(), &mut out) };
+            Self::set_output(mat, out);
+            ret
+        };
+        custom_layer::run_ncnn(
+            extract,
+            format!("Error running extract on layer `{}`", name),
+        )
+    }
+
+    /// Runs network inferrence and returns output tensor by a given blob index.
+    pub fn extract_index(&mut self, index: i32, mat: &mut crate::mat::Mat) -> anyhow::Result<()> {
+        let extract = || {
+            let mut out = std::ptr::null_mut();
+            let ret = unsafe { ncnn_extractor_extract_index(self.ptr, index, &mut out) };
+            Self::set_output(mat, out);
+            ret
+        };
+        custom_layer::run_ncnn(extract, format!("Error running extract on blob {}", index))
+    }
+
+    /// Returns output tensors by given names, from a single inferrence.
+    pub fn extract_many(&mut self, names: &[&str]) -> anyhow::Result<Vec<crate::mat::Mat>> {
+        names
+            .iter()
+            .map(|name| {
+                let mut mat = crate::mat::Mat::new();
+                self.extract(name, &mut mat)?;
+                Ok(mat)
+            })
+            .collect()
+    }
+
+    // ncnn allocates a new matrix header for every extract.
+    fn set_output(mat: &mut crate::mat::Mat, out: ncnn_mat_t) {
+        if !out.is_null() {
+            *mat = unsafe { crate::mat::Mat::from_ptr(out) };
+        }
+    }
 }
 
 impl<'a> Drop for Extractor<'a> {
@@ -47,3 +94,34 @@ impl<'a> Drop for Extractor<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{DataReader, Mat, Net};
+
+    #[test]
+    fn extract_many_outputs() {
+        let mut net = Net::new();
+        net.load_param_memory(
+            "7767517\n4 5\n\
+             Input data 0 1 data\n\
+             Split split 1 2 data a b\n\
+             ReLU relu 1 1 a relu\n\
+             AbsVal abs 1 1 b abs\n",
+        )
+        .unwrap();
+        net.load_model_datareader(&DataReader::empty()).unwrap();
+
+        let input = Mat::from_vec_1d(vec![-1.0, 2.0], 2).unwrap();
+        let mut ex = net.create_extractor();
+        ex.input("data", &input).unwrap();
+        let outputs = ex.extract_many(&["relu", "abs"]).unwrap();
+        assert_eq!(&[0.0, 2.0], outputs[0].as_slice::<f32>().unwrap());
+        assert_eq!(&[1.0, 2.0], outputs[1].as_slice::<f32>().unwrap());
+
+        let mut relu = Mat::new();
+        ex.extract("relu", &mut relu).unwrap();
+        assert_eq!(&[0.0, 2.0], relu.as_slice::<f32>().unwrap());
+        assert!(ex.extract_many(&["missing"]).is_err());
+    }
+}
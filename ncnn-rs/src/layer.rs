@@ -0,0 +1,265 @@
+use crate::mat::Mat;
+use crate::modelbin::ModelBin;
+use crate::option::Option as NcnnOption;
+use crate::paramdict::ParamDict;
+use ncnn_bind::*;
+use std::ffi::CString;
+
+/// Builtin ncnn layer run on its own, outside of a [Net](crate::Net).
+///
+/// Set up with [Layer::load_param], [Layer::load_model] for layers with weights and
+/// [Layer::create_pipeline], in this order, before forwarding. Outputs may be packed
+/// (see [Mat::elempack]) unless packing is disabled in the pipeline options.
+pub struct Layer {
+    ptr: ncnn_layer_t,
+    weights: bool,
+    pipeline: bool,
+}
+
+unsafe impl Send for Layer {}
+
+impl Layer {
+    /// Creates a layer by type name, e.g. `Convolution`.
+    pub fn new(layer_type: &str) -> anyhow::Result<Self> {
+        let typeindex = Self::type_to_index(layer_type)
+            .ok_or_else(|| anyhow::anyhow!("Unknown layer type `{}`", layer_type))?;
+        Self::from_typeindex(typeindex)
+    }
+
+    /// Creates a layer by type index, see [Layer::type_to_index].
+    pub fn from_typeindex(typeindex: i32) -> anyhow::Result<Self> {
+        let ptr = unsafe { ncnn_layer_create_by_typeindex(typeindex) };
+        if unsafe { (*ptr).pthis.is_null() } {
+            unsafe { ncnn_layer_destroy(ptr) };
+            anyhow::bail!("Unknown layer type index {}", typeindex);
+        }
+        Ok(Self {
+            ptr,
+            weights: false,
+            pipeline: false,
+        })
+    }
+
+    /// Returns index of a builtin layer type.
+    pub fn type_to_index(layer_type: &str) -> Option<i32> {
+        let layer_type = CString::new(layer_type).ok()?;
+        let typeindex = unsafe { ncnn_layer_type_to_index(layer_type.as_ptr()) };
+        (typeindex >= 0).then_some(typeindex)
+    }
+
+    pub fn typeindex(&self) -> i32 {
+        unsafe { ncnn_layer_get_typeindex(self.ptr) }
+    }
+
+    /// Layer has exactly one input and one output, see [Layer::forward].
+    pub fn one_blob_only(&self) -> bool {
+        unsafe { ncnn_layer_get_one_blob_only(self.ptr) != 0 }
+    }
+
+    /// Layer can overwrite its inputs, see [Layer::forward_inplace].
+    pub fn support_inplace(&self) -> bool {
+        unsafe { ncnn_layer_get_support_inplace(self.ptr) != 0 }
+    }
+
+    /// Reads layer params, missing ones take their defaults. Weights have to be loaded
+    /// again afterwards.
+    pub fn load_param(&mut self, pd: &ParamDict) -> anyhow::Result<()> {
+        self.check_no_pipeline()?;
+        let f = unsafe { (*self.ptr).load_param }.expect("layer without load_param");
+        self.weights = false;
+        if unsafe { f(self.ptr, pd.ptr()) } != 0 {
+            anyhow::bail!("Error loading layer params");
+        }
+        Ok(())
+    }
+
+    /// Reads layer weights.
+    pub fn load_model(&mut self, mb: &ModelBin) -> anyhow::Result<()> {
+        self.check_no_pipeline()?;
+        let f = unsafe { (*self.ptr).load_model }.expect("layer without load_model");
+        if unsafe { f(self.ptr, mb.ptr()) } != 0 {
+            anyhow::bail!("Error loading layer weights");
+        }
+        self.weights = true;
+        Ok(())
+    }
+
+    /// Prepares the layer for forwarding with the given options, the same options must
+    /// be passed to forward calls.
+    ///
+    /// Fails for layers with weights when [Layer::load_model] was not called.
+    pub fn create_pipeline(&mut self, opt: &NcnnOption) -> anyhow::Result<()> {
+        if !self.weights {
+            // Layers without weights load nothing, the others fail on the empty model bin.
+            self.load_model(&ModelBin::from_mat_array(Vec::new()))
+                .map_err(|_| anyhow::anyhow!("Layer weights are not loaded"))?;
+        }
+        let f = unsafe { (*self.ptr).create_pipeline }.expect("layer without create_pipeline");
+        if unsafe { f(self.ptr, opt.ptr()) } != 0 {
+            anyhow::bail!("Error creating layer pipeline");
+        }
+        self.pipeline = true;
+        Ok(())
+    }
+
+    /// Releases pipeline resources, dropping the layer does it with default options.
+    pub fn destroy_pipeline(&mut self, opt: &NcnnOption) -> anyhow::Result<()> {
+        if !self.pipeline {
+            return Ok(());
+        }
+        let f = unsafe { (*self.ptr).destroy_pipeline }.expect("layer without destroy_pipeline");
+        self.pipeline = false;
+        if unsafe { f(self.ptr, opt.ptr()) } != 0 {
+            anyhow::bail!("Error destroying layer pipeline");
+        }
+        Ok(())
+    }
+
+    /// Computes `top` from `bottom`, for [one blob](Layer::one_blob_only) layers.
+    pub fn forward(&self, bottom: &Mat, top: &mut Mat, opt: &NcnnOption) -> anyhow::Result<()> {
+        self.check_pipeline()?;
+        let f = forward_fn(unsafe { (*self.ptr).forward_1 })?;
+        let mut out = core::ptr::null_mut();
+        let ret = unsafe { f(self.ptr, bottom.ptr(), &mut out, opt.ptr()) };
+        if !out.is_null() {
+            *top = unsafe { Mat::from_ptr(out) };
+        }
+        if ret != 0 {
+            anyhow::bail!("Error running layer forward");
+        }
+        Ok(())
+    }
+
+    /// Computes `tops` from `bottoms`, `tops` must hold a matrix per layer output.
+    ///
+    /// Fails for [one blob](Layer::one_blob_only) layers, use [Layer::forward] for them.
+    pub fn forward_n(
+        &self,
+        bottoms: &[Mat],
+        tops: &mut [Mat],
+        opt: &NcnnOption,
+    ) -> anyhow::Result<()> {
+        self.check_pipeline()?;
+        if self.one_blob_only() {
+            anyhow::bail!("Layer takes one blob, use `forward`");
+        }
+        if bottoms.is_empty() || tops.is_empty() {
+            anyhow::bail!(
+                "Expected bottoms and tops, got {} and {}",
+                bottoms.len(),
+                tops.len()
+            );
+        }
+        let f = forward_fn(unsafe { (*self.ptr).forward_n })?;
+        let bottoms = bottoms.iter().map(Mat::ptr).collect::<Vec<_>>();
+        let mut outs = vec![core::ptr::null_mut(); tops.len()];
+        let ret = unsafe {
+            f(
+                self.ptr,
+                bottoms.as_ptr(),
+                bottoms.len() as i32,
+                outs.as_mut_ptr(),
+                outs.len() as i32,
+                opt.ptr(),
+            )
+        };
+        for (top, out) in tops.iter_mut().zip(outs) {
+            if !out.is_null() {
+                *top = unsafe { Mat::from_ptr(out) };
+            }
+        }
+        if ret != 0 {
+            anyhow::bail!("Error running layer forward");
+        }
+        Ok(())
+    }
+
+    /// Overwrites `blob` with the output, for layers [supporting](Layer::support_inplace) it.
+    pub fn forward_inplace(&self, blob: &mut Mat, opt: &NcnnOption) -> anyhow::Result<()> {
+        self.check_pipeline()?;
+        let f = forward_fn(unsafe { (*self.ptr).forward_inplace_1 })?;
+        if unsafe { f(self.ptr, blob.ptr(), opt.ptr()) } != 0 {
+            anyhow::bail!("Error running layer forward");
+        }
+        Ok(())
+    }
+
+    fn check_pipeline(&self) -> anyhow::Result<()> {
+        if !self.pipeline {
+            anyhow::bail!("Layer pipeline is not created");
+        }
+        Ok(())
+    }
+
+    /// Params and weights are baked into the pipeline, they change only without one.
+    fn check_no_pipeline(&self) -> anyhow::Result<()> {
+        if self.pipeline {
+            anyhow::bail!("Layer pipeline is created, destroy it first");
+        }
+        Ok(())
+    }
+}
+
+fn forward_fn<F>(f: Option<F>) -> anyhow::Result<F> {
+    f.ok_or_else(|| anyhow::anyhow!("Layer does not implement this forward"))
+}
+
+impl Drop for Layer {
+    fn drop(&mut self) {
+        let _ = self.destroy_pipeline(&NcnnOption::new());
+        unsafe { ncnn_layer_destroy(self.ptr) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Layer, Mat, Option, ParamDict};
+
+    #[test]
+    fn unknown_layer_type() {
+        assert!(Layer::new("NoSuchLayer").is_err());
+        assert_eq!(None, Layer::type_to_index("NoSuchLayer"));
+    }
+
+    #[test]
+    fn forward_single_layers() {
+        let mut opt = Option::new();
+        opt.set_num_threads(1);
+
+        let mut relu = Layer::new("ReLU").unwrap();
+        relu.load_param(&ParamDict::new()).unwrap();
+        relu.create_pipeline(&opt).unwrap();
+        let mut blob = Mat::from_vec_1d(vec![-1.0, 2.0], 2).unwrap();
+        relu.forward_inplace(&mut blob, &opt).unwrap();
+        assert_eq!(&[0.0, 2.0], blob.as_slice::<f32>().unwrap());
+
+        let mut softmax = Layer::new("Softmax").unwrap();
+        softmax.load_param(&ParamDict::new()).unwrap();
+        assert!(softmax.forward_inplace(&mut blob, &opt).is_err());
+        softmax.create_pipeline(&opt).unwrap();
+        softmax.forward_inplace(&mut blob, &opt).unwrap();
+        let sum: f32 = blob.as_slice::<f32>().unwrap().iter().sum();
+        assert!((sum - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn forward_checks_setup() {
+        let opt = Option::new();
+        let mut inner_product = Layer::new("InnerProduct").unwrap();
+        inner_product
+            .load_param(&"0=2 2=4".parse::<ParamDict>().unwrap())
+            .unwrap();
+        assert!(inner_product.create_pipeline(&opt).is_err());
+
+        let mut concat = Layer::new("Concat").unwrap();
+        concat.load_param(&ParamDict::new()).unwrap();
+        concat.create_pipeline(&opt).unwrap();
+        let bottoms = [Mat::from_vec_1d(vec![1.0], 1).unwrap()];
+        assert!(concat.forward_n(&bottoms, &mut [], &opt).is_err());
+        assert!(concat.forward_n(&[], &mut [Mat::new()], &opt).is_err());
+        let mut tops = [Mat::new()];
+        concat.forward_n(&bottoms, &mut tops, &opt).unwrap();
+        assert_eq!(1, tops[0].w());
+        assert!(concat.load_param(&ParamDict::new()).is_err());
+    }
+}
@@ -1,16 +1,34 @@
 mod allocator;
+mod custom_layer;
 mod datareader;
 mod extractor;
+mod layer;
 mod mat;
+#[cfg(feature = "image")]
+mod mat_image;
+#[cfg(feature = "ndarray")]
+mod mat_ndarray;
+mod modelbin;
 mod net;
 mod option;
+pub mod param;
+mod param_id;
+mod paramdict;
+pub mod pixel;
 
 pub use allocator::*;
+pub use custom_layer::CustomLayer;
 pub use datareader::*;
 pub use extractor::*;
+pub use layer::*;
 pub use mat::*;
+#[cfg(feature = "image")]
+pub use mat_image::*;
+pub use modelbin::*;
 pub use net::*;
 pub use option::*;
+pub use param_id::*;
+pub use paramdict::*;
 
 pub use ncnn_bind as ffi;
 
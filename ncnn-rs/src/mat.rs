@@ -1,5 +1,7 @@
 use crate::allocator::Allocator;
 use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, Index, IndexMut};
 use ncnn_bind::*;
 use std::os::raw::c_void;
 
@@ -138,10 +140,173 @@ impl MatPixelType {
             | MatPixelType::RGBAtoRGB => 4,
         }
     }
+
+    /// Bytes per pixel of the converted pixels.
+    fn target_stride(&self) -> i32 {
+        match self {
+            MatPixelType::GRAY
+            | MatPixelType::RGBtoGRAY
+            | MatPixelType::BGRtoGRAY
+            | MatPixelType::RGBAtoGRAY
+            | MatPixelType::BGRAtoGRAY => 1,
+            MatPixelType::BGR
+            | MatPixelType::RGB
+            | MatPixelType::RGBtoBGR
+            | MatPixelType::BGRtoRGB
+            | MatPixelType::GRAYtoRGB
+            | MatPixelType::GRAYtoBGR
+            | MatPixelType::RGBAtoRGB
+            | MatPixelType::RGBAtoBGR
+            | MatPixelType::BGRAtoRGB
+            | MatPixelType::BGRAtoBGR => 3,
+            MatPixelType::BGRA
+            | MatPixelType::RGBA
+            | MatPixelType::RGBtoRGBA
+            | MatPixelType::RGBtoBGRA
+            | MatPixelType::BGRtoRGBA
+            | MatPixelType::BGRtoBGRA
+            | MatPixelType::GRAYtoRGBA
+            | MatPixelType::GRAYtoBGRA
+            | MatPixelType::RGBAtoBGRA
+            | MatPixelType::BGRAtoRGBA => 4,
+        }
+    }
+
+    /// Whether ncnn can write matrix into pixels of this type.
+    fn supports_to_pixels(&self) -> bool {
+        matches!(
+            self,
+            MatPixelType::BGR
+                | MatPixelType::BGRA
+                | MatPixelType::GRAY
+                | MatPixelType::RGB
+                | MatPixelType::RGBA
+                | MatPixelType::RGBtoBGR
+                | MatPixelType::BGRtoRGB
+                | MatPixelType::RGBtoRGBA
+                | MatPixelType::BGRtoBGRA
+                | MatPixelType::RGBtoBGRA
+                | MatPixelType::BGRtoRGBA
+                | MatPixelType::GRAYtoRGBA
+                | MatPixelType::GRAYtoBGRA
+                | MatPixelType::RGBAtoBGRA
+                | MatPixelType::BGRAtoRGBA
+        )
+    }
+}
+
+/// Scalar types that can be viewed inside matrix data.
+///
+/// # Safety
+///
+/// Implementors must be plain old data types for which every bit pattern is a valid value.
+pub unsafe trait MatElement: Copy + 'static {}
+
+unsafe impl MatElement for f32 {}
+unsafe impl MatElement for i32 {}
+unsafe impl MatElement for u32 {}
+unsafe impl MatElement for i16 {}
+unsafe impl MatElement for u16 {}
+unsafe impl MatElement for i8 {}
+unsafe impl MatElement for u8 {}
+
+/// Rectangle in pixel coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Fails unless the rectangle is non-empty and lies within an image of a given size.
+    fn check_inside(&self, width: i32, height: i32) -> anyhow::Result<()> {
+        if self.x < 0
+            || self.y < 0
+            || self.width <= 0
+            || self.height <= 0
+            || i64::from(self.x) + i64::from(self.width) > i64::from(width)
+            || i64::from(self.y) + i64::from(self.height) > i64::from(height)
+        {
+            anyhow::bail!("{:?} is outside of {}x{} image", self, width, height);
+        }
+        Ok(())
+    }
+}
+
+/// Blob shape as stored in `-23330` shape hints of optimized params.
+///
+/// Axes beyond `dims` are 1, e.g. `-23330=4,3,227,227,3` is
+/// `Shape { dims: 3, w: 227, h: 227, c: 3 }`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Shape {
+    pub dims: i32,
+    pub w: i32,
+    pub h: i32,
+    pub c: i32,
+}
+
+impl Shape {
+    pub fn new(dims: i32, w: i32, h: i32, c: i32) -> Self {
+        Self { dims, w, h, c }
+    }
+
+    /// Allocates an uninitialized matrix of this shape.
+    pub fn new_mat(&self, alloc: Option<&Allocator>) -> anyhow::Result<Mat> {
+        match self.dims {
+            1 => Ok(Mat::new_1d(self.w, alloc)),
+            2 => Ok(Mat::new_2d(self.w, self.h, alloc)),
+            3 => Ok(Mat::new_3d(self.w, self.h, self.c, alloc)),
+            _ => anyhow::bail!("Cannot allocate matrix of {:?}", self),
+        }
+    }
+
+    /// Fails unless the unpacked matrix has exactly this shape.
+    pub fn check(&self, mat: &Mat) -> anyhow::Result<()> {
+        let actual = Shape::of(mat);
+        if actual != *self {
+            anyhow::bail!("Expected matrix of {:?}, got {:?}", self, actual);
+        }
+        Ok(())
+    }
+
+    /// Shape of a matrix with packed elements spread back over the outermost axis.
+    fn of(mat: &Mat) -> Self {
+        let pack = mat.elempack();
+        match mat.dims() {
+            1 => Self::new(1, mat.w() * pack, 1, 1),
+            2 => Self::new(2, mat.w(), mat.h() * pack, 1),
+            3 => Self::new(3, mat.w(), mat.h(), mat.c() * pack),
+            dims => Self::new(dims, mat.w(), mat.h(), mat.c()),
+        }
+    }
+}
+
+/// Formats used axes as `w`, `wxh` or `wxhxc`.
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dims {
+            1 => write!(f, "{}", self.w),
+            2 => write!(f, "{}x{}", self.w, self.h),
+            _ => write!(f, "{}x{}x{}", self.w, self.h, self.c),
+        }
+    }
 }
 
 pub struct Mat {
     ptr: ncnn_mat_t,
+    // Backing buffer of matrices constructed from owned Rust data, must outlive `ptr`.
+    _storage: Vec<f32>,
 }
 
 // Mat is basically a glorified atomically refcounted matrix.
@@ -162,6 +327,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            _storage: Vec::new(),
         }
     }
 
@@ -175,6 +341,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            _storage: Vec::new(),
         }
     }
 
@@ -189,6 +356,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            _storage: Vec::new(),
         }
     }
 
@@ -204,6 +372,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            _storage: Vec::new(),
         }
     }
 
@@ -219,6 +388,7 @@ impl Mat {
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            _storage: Vec::new(),
         }
     }
 
@@ -240,6 +410,7 @@ impl Mat {
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            _storage: Vec::new(),
         }
     }
 
@@ -263,6 +434,7 @@ impl Mat {
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            _storage: Vec::new(),
         }
     }
 
@@ -288,7 +460,70 @@ impl Mat {
                 data,
                 alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
             ),
+            _storage: Vec::new(),
+        }
+    }
+
+    /// Constructs 1D matrix taking ownership of the given data.
+    pub fn from_vec_1d(data: Vec<f32>, w: i32) -> anyhow::Result<Mat> {
+        Self::from_vec(data, 1, [w, 1, 1, 1], |data| unsafe {
+            ncnn_mat_create_external_1d(w, data, core::ptr::null_mut())
+        })
+    }
+
+    /// Constructs 2D matrix taking ownership of the given row-major data.
+    pub fn from_vec_2d(data: Vec<f32>, w: i32, h: i32) -> anyhow::Result<Mat> {
+        Self::from_vec(data, 2, [w, h, 1, 1], |data| unsafe {
+            ncnn_mat_create_external_2d(w, h, data, core::ptr::null_mut())
+        })
+    }
+
+    /// Constructs 3D matrix taking ownership of the given channel-major data.
+    ///
+    /// Data is copied once if channels have to be padded to ncnn `cstep` alignment.
+    pub fn from_vec_3d(data: Vec<f32>, w: i32, h: i32, c: i32) -> anyhow::Result<Mat> {
+        Self::from_vec(data, 3, [w, h, 1, c], |data| unsafe {
+            ncnn_mat_create_external_3d(w, h, c, data, core::ptr::null_mut())
+        })
+    }
+
+    /// Constructs 4D matrix taking ownership of the given channel-major data.
+    ///
+    /// Data is copied once if channels have to be padded to ncnn `cstep` alignment.
+    pub fn from_vec_4d(data: Vec<f32>, w: i32, h: i32, d: i32, c: i32) -> anyhow::Result<Mat> {
+        Self::from_vec(data, 4, [w, h, d, c], |data| unsafe {
+            ncnn_mat_create_external_4d(w, h, d, c, data, core::ptr::null_mut())
+        })
+    }
+
+    fn from_vec(
+        mut data: Vec<f32>,
+        dims: i32,
+        shape: [i32; 4],
+        create: impl FnOnce(*mut c_void) -> ncnn_mat_t,
+    ) -> anyhow::Result<Mat> {
+        let (plane, cstep, channels) = external_layout(dims, shape)?;
+        if data.len() != plane * channels {
+            anyhow::bail!(
+                "Expected data length {}, provided {}",
+                plane * channels,
+                data.len()
+            );
         }
+
+        if cstep != plane {
+            let mut padded = vec![0.0; cstep * channels];
+            for (dst, src) in padded.chunks_mut(cstep).zip(data.chunks(plane)) {
+                dst[..plane].copy_from_slice(src);
+            }
+            data = padded;
+        }
+
+        // Moving the vector into the matrix keeps its heap buffer in place.
+        Ok(Self {
+            ptr: create(data.as_mut_ptr() as *mut c_void),
+            _storage: data,
+        })
     }
 
     /// Constructs matrix from a pixel byte array
@@ -315,6 +550,7 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            _storage: Vec::new(),
         })
     }
 
@@ -346,9 +582,213 @@ impl Mat {
                     alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
                 )
             },
+            _storage: Vec::new(),
         })
     }
 
+    /// Constructs matrix from a pixel byte array with rows `stride` bytes apart.
+    ///
+    /// Rows may be padded, the last row only needs to hold `width` pixels.
+    pub fn from_pixels_stride(
+        data: &[u8],
+        pixel_type: MatPixelType,
+        width: i32,
+        height: i32,
+        stride: i32,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        check_pixels(data, &pixel_type, width, height, stride)?;
+
+        Ok(Self {
+            ptr: unsafe {
+                ncnn_mat_from_pixels(
+                    data.as_ptr(),
+                    pixel_type.to_int(),
+                    width,
+                    height,
+                    stride,
+                    alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
+                )
+            },
+            _storage: Vec::new(),
+        })
+    }
+
+    /// Constructs matrix from resizing a pixel byte array with rows `stride` bytes apart.
+    ///
+    /// Rows may be padded, the last row only needs to hold `width` pixels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_pixels_resize_stride(
+        data: &[u8],
+        pixel_type: MatPixelType,
+        width: i32,
+        height: i32,
+        stride: i32,
+        target_width: i32,
+        target_height: i32,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        check_pixels(data, &pixel_type, width, height, stride)?;
+
+        Ok(Self {
+            ptr: unsafe {
+                ncnn_mat_from_pixels_resize(
+                    data.as_ptr(),
+                    pixel_type.to_int(),
+                    width,
+                    height,
+                    stride,
+                    target_width,
+                    target_height,
+                    alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
+                )
+            },
+            _storage: Vec::new(),
+        })
+    }
+
+    /// Constructs matrix from a region of a pixel byte array.
+    ///
+    /// Rows of the source image are `stride` bytes apart.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_pixels_roi(
+        data: &[u8],
+        pixel_type: MatPixelType,
+        width: i32,
+        height: i32,
+        stride: i32,
+        roi: Rect,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        check_pixels(data, &pixel_type, width, height, stride)?;
+        roi.check_inside(width, height)?;
+
+        Ok(Self {
+            ptr: unsafe {
+                ncnn_mat_from_pixels_roi(
+                    data.as_ptr(),
+                    pixel_type.to_int(),
+                    width,
+                    height,
+                    stride,
+                    roi.x,
+                    roi.y,
+                    roi.width,
+                    roi.height,
+                    alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
+                )
+            },
+            _storage: Vec::new(),
+        })
+    }
+
+    /// Constructs matrix from resizing a region of a pixel byte array.
+    ///
+    /// Rows of the source image are `stride` bytes apart.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_pixels_roi_resize(
+        data: &[u8],
+        pixel_type: MatPixelType,
+        width: i32,
+        height: i32,
+        stride: i32,
+        roi: Rect,
+        target_width: i32,
+        target_height: i32,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        check_pixels(data, &pixel_type, width, height, stride)?;
+        roi.check_inside(width, height)?;
+
+        Ok(Self {
+            ptr: unsafe {
+                ncnn_mat_from_pixels_roi_resize(
+                    data.as_ptr(),
+                    pixel_type.to_int(),
+                    width,
+                    height,
+                    stride,
+                    roi.x,
+                    roi.y,
+                    roi.width,
+                    roi.height,
+                    target_width,
+                    target_height,
+                    alloc.map(Allocator::ptr).unwrap_or(core::ptr::null_mut()),
+                )
+            },
+            _storage: Vec::new(),
+        })
+    }
+
+    /// Writes matrix into a pixel byte array.
+    ///
+    /// Pixel type describes the conversion from matrix channels, e.g. [MatPixelType::BGRtoRGB]
+    /// for a BGR matrix written as RGB pixels.
+    pub fn to_pixels(&self, data: &mut [u8], pixel_type: MatPixelType) -> anyhow::Result<()> {
+        let (width, height) = (self.w(), self.h());
+        let stride = self.check_to_pixels(data, &pixel_type, width, height)?;
+
+        unsafe { ncnn_mat_to_pixels(self.ptr, data.as_mut_ptr(), pixel_type.to_int(), stride) };
+        Ok(())
+    }
+
+    /// Writes matrix into a pixel byte array resized to a target size.
+    pub fn to_pixels_resize(
+        &self,
+        data: &mut [u8],
+        pixel_type: MatPixelType,
+        target_width: i32,
+        target_height: i32,
+    ) -> anyhow::Result<()> {
+        let stride = self.check_to_pixels(data, &pixel_type, target_width, target_height)?;
+
+        unsafe {
+            ncnn_mat_to_pixels_resize(
+                self.ptr,
+                data.as_mut_ptr(),
+                pixel_type.to_int(),
+                target_width,
+                target_height,
+                stride,
+            )
+        };
+        Ok(())
+    }
+
+    /// Returns the row stride of the written pixels.
+    fn check_to_pixels(
+        &self,
+        data: &[u8],
+        pixel_type: &MatPixelType,
+        width: i32,
+        height: i32,
+    ) -> anyhow::Result<i32> {
+        if !pixel_type.supports_to_pixels() {
+            anyhow::bail!("Pixel type conversion is not supported by `to_pixels`");
+        }
+        if self.is_empty() || self.c() != pixel_type.stride() || self.elemsize() != 4 {
+            anyhow::bail!(
+                "Expected matrix of {} float channels, got {:?}",
+                pixel_type.stride(),
+                self
+            );
+        }
+        if width <= 0 || height <= 0 {
+            anyhow::bail!("Invalid image size {}x{}", width, height);
+        }
+        let stride = width
+            .checked_mul(pixel_type.target_stride())
+            .ok_or_else(|| anyhow::anyhow!("Image width {} is too large", width))?;
+        let len = (stride as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(|| anyhow::anyhow!("Image size {}x{} is too large", width, height))?;
+        if data.len() != len {
+            anyhow::bail!("Expected data length {}, provided {}", len, data.len());
+        }
+        Ok(stride)
+    }
+
     pub fn substract_mean_normalize(&mut self, mean_vals: &[f32], norm_vals: &[f32]) {
         let channels = self.c() as usize;
         assert_eq!(mean_vals.len(), channels);
@@ -413,12 +853,158 @@ impl Mat {
         unsafe { ncnn_mat_get_data(self.ptr) }
     }
 
+    /// Returns true if matrix holds no data.
+    pub fn is_empty(&self) -> bool {
+        self.data().is_null() || self.cstep() * self.c() as usize == 0
+    }
+
+    /// Returns the whole matrix data as a slice of scalars.
+    ///
+    /// Fails if channels are padded to `cstep`, use [Mat::channel] in that case.
+    pub fn as_slice<T: MatElement>(&self) -> anyhow::Result<&[T]> {
+        let len = self.contiguous_len::<T>()?;
+        Ok(unsafe { slice_or_empty(self.data() as *const T, len) })
+    }
+
+    /// Returns the whole matrix data as a mutable slice of scalars.
+    ///
+    /// Fails if channels are padded to `cstep`, use [Mat::channel_mut] in that case.
+    pub fn as_mut_slice<T: MatElement>(&mut self) -> anyhow::Result<&mut [T]> {
+        let len = self.contiguous_len::<T>()?;
+        Ok(unsafe { slice_or_empty_mut(self.data() as *mut T, len) })
+    }
+
+    /// Returns scalars of a single channel, excluding `cstep` padding.
+    pub fn channel<T: MatElement>(&self, c: i32) -> anyhow::Result<&[T]> {
+        let (offset, len) = self.channel_range::<T>(c)?;
+        Ok(unsafe { slice_or_empty((self.data() as *const T).add(offset), len) })
+    }
+
+    /// Returns mutable scalars of a single channel, excluding `cstep` padding.
+    pub fn channel_mut<T: MatElement>(&mut self, c: i32) -> anyhow::Result<&mut [T]> {
+        let (offset, len) = self.channel_range::<T>(c)?;
+        Ok(unsafe { slice_or_empty_mut((self.data() as *mut T).add(offset), len) })
+    }
+
+    /// Returns scalars of a single row of a 2D matrix.
+    pub fn row<T: MatElement>(&self, y: i32) -> anyhow::Result<&[T]> {
+        let (offset, len) = self.row_range::<T>(y)?;
+        Ok(unsafe { slice_or_empty((self.data() as *const T).add(offset), len) })
+    }
+
+    /// Returns mutable scalars of a single row of a 2D matrix.
+    pub fn row_mut<T: MatElement>(&mut self, y: i32) -> anyhow::Result<&mut [T]> {
+        let (offset, len) = self.row_range::<T>(y)?;
+        Ok(unsafe { slice_or_empty_mut((self.data() as *mut T).add(offset), len) })
+    }
+
+    /// Number of scalars stored in a single element, i.e. `elempack`.
+    pub(crate) fn check_element<T: MatElement>(&self) -> anyhow::Result<usize> {
+        let elempack = self.elempack().max(1) as usize;
+        let scalar_size = self.elemsize() as usize / elempack;
+        if scalar_size != std::mem::size_of::<T>() {
+            anyhow::bail!(
+                "Matrix element size {} (elempack {}) does not match {} bytes of `{}`",
+                self.elemsize(),
+                elempack,
+                std::mem::size_of::<T>(),
+                std::any::type_name::<T>()
+            );
+        }
+        Ok(elempack)
+    }
+
+    /// Number of scalars in a single channel, excluding padding.
+    fn channel_len<T: MatElement>(&self) -> anyhow::Result<usize> {
+        let elempack = self.check_element::<T>()?;
+        Ok((self.w() * self.h() * self.d()) as usize * elempack)
+    }
+
+    fn contiguous_len<T: MatElement>(&self) -> anyhow::Result<usize> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+        let len = self.channel_len::<T>()?;
+        let elempack = self.elempack().max(1) as usize;
+        if self.c() > 1 && self.cstep() * elempack != len {
+            anyhow::bail!(
+                "Matrix channels are not contiguous (cstep {}), use channel views instead",
+                self.cstep()
+            );
+        }
+        Ok(len * self.c() as usize)
+    }
+
+    fn channel_range<T: MatElement>(&self, c: i32) -> anyhow::Result<(usize, usize)> {
+        if c < 0 || c >= self.c() || self.is_empty() {
+            anyhow::bail!("Channel {} out of range, matrix has {}", c, self.c());
+        }
+        let len = self.channel_len::<T>()?;
+        let elempack = self.elempack().max(1) as usize;
+        Ok((self.cstep() * elempack * c as usize, len))
+    }
+
+    fn row_range<T: MatElement>(&self, y: i32) -> anyhow::Result<(usize, usize)> {
+        if self.dims() != 2 {
+            anyhow::bail!("Row access expects a 2D matrix, got {} dims", self.dims());
+        }
+        if y < 0 || y >= self.h() {
+            anyhow::bail!("Row {} out of range, matrix has {}", y, self.h());
+        }
+        let elempack = self.check_element::<T>()?;
+        let len = self.w() as usize * elempack;
+        Ok((len * y as usize, len))
+    }
+
+    /// Offset of a single `f32` element, panicking on layout or bounds mismatch.
+    fn element_offset(&self, dims: i32, c: usize, y: usize, x: usize) -> usize {
+        assert_eq!(self.dims(), dims, "Matrix dims mismatch");
+        assert_eq!(
+            self.elempack(),
+            1,
+            "Indexing packed matrix is not supported"
+        );
+        assert_eq!(self.elemsize(), 4, "Indexing expects `f32` elements");
+        assert!(
+            c < self.c() as usize && y < self.h() as usize && x < self.w() as usize,
+            "Index ({}, {}, {}) out of bounds ({}, {}, {})",
+            c,
+            y,
+            x,
+            self.c(),
+            self.h(),
+            self.w()
+        );
+        self.cstep() * c + self.w() as usize * y + x
+    }
+
     pub(crate) fn ptr(&self) -> ncnn_mat_t {
         self.ptr
     }
 
-    pub(crate) fn mut_ptr(&mut self) -> *mut ncnn_mat_t {
-        &mut self.ptr
+    /// Takes ownership of a matrix created by ncnn.
+    pub(crate) unsafe fn from_ptr(ptr: ncnn_mat_t) -> Self {
+        Self {
+            ptr,
+            _storage: Vec::new(),
+        }
+    }
+
+    /// Constructs a matrix sharing refcounted data with a matrix owned by ncnn.
+    pub(crate) unsafe fn share(ptr: ncnn_mat_t) -> Self {
+        Self::from_ptr(ncnn_ext_mat_share(ptr))
+    }
+
+    /// Hands the matrix over to ncnn, to be released with `ncnn_mat_destroy`.
+    ///
+    /// Data backed by Rust storage is cloned, as the storage goes away with `self`.
+    pub(crate) fn into_raw(self) -> ncnn_mat_t {
+        if self._storage.is_empty() {
+            let this = core::mem::ManuallyDrop::new(self);
+            this.ptr
+        } else {
+            unsafe { ncnn_mat_clone(self.ptr, core::ptr::null_mut()) }
+        }
     }
 }
 
@@ -426,6 +1012,7 @@ impl Default for Mat {
     fn default() -> Self {
         Self {
             ptr: unsafe { ncnn_mat_create() },
+            _storage: Vec::new(),
         }
     }
 }
@@ -444,6 +1031,54 @@ impl fmt::Debug for Mat {
     }
 }
 
+impl Index<usize> for Mat {
+    type Output = f32;
+
+    fn index(&self, x: usize) -> &f32 {
+        let offset = self.element_offset(1, 0, 0, x);
+        unsafe { &*(self.data() as *const f32).add(offset) }
+    }
+}
+
+impl IndexMut<usize> for Mat {
+    fn index_mut(&mut self, x: usize) -> &mut f32 {
+        let offset = self.element_offset(1, 0, 0, x);
+        unsafe { &mut *(self.data() as *mut f32).add(offset) }
+    }
+}
+
+impl Index<(usize, usize)> for Mat {
+    type Output = f32;
+
+    fn index(&self, (y, x): (usize, usize)) -> &f32 {
+        let offset = self.element_offset(2, 0, y, x);
+        unsafe { &*(self.data() as *const f32).add(offset) }
+    }
+}
+
+impl IndexMut<(usize, usize)> for Mat {
+    fn index_mut(&mut self, (y, x): (usize, usize)) -> &mut f32 {
+        let offset = self.element_offset(2, 0, y, x);
+        unsafe { &mut *(self.data() as *mut f32).add(offset) }
+    }
+}
+
+impl Index<(usize, usize, usize)> for Mat {
+    type Output = f32;
+
+    fn index(&self, (c, y, x): (usize, usize, usize)) -> &f32 {
+        let offset = self.element_offset(3, c, y, x);
+        unsafe { &*(self.data() as *const f32).add(offset) }
+    }
+}
+
+impl IndexMut<(usize, usize, usize)> for Mat {
+    fn index_mut(&mut self, (c, y, x): (usize, usize, usize)) -> &mut f32 {
+        let offset = self.element_offset(3, c, y, x);
+        unsafe { &mut *(self.data() as *mut f32).add(offset) }
+    }
+}
+
 impl Drop for Mat {
     fn drop(&mut self) {
         unsafe {
@@ -452,9 +1087,162 @@ impl Drop for Mat {
     }
 }
 
+/// Matrix borrowing a mutable slice, it cannot outlive the borrowed data.
+///
+/// Dereferences to [Mat], so it can be passed to [crate::Extractor::input].
+pub struct MatView<'a> {
+    mat: Mat,
+    _phantom: PhantomData<&'a mut [f32]>,
+}
+
+impl<'a> MatView<'a> {
+    /// Constructs 1D matrix view of the given data.
+    pub fn new_1d(data: &'a mut [f32], w: i32) -> anyhow::Result<Self> {
+        Self::new(data, 1, [w, 1, 1, 1], |data| unsafe {
+            Mat::new_external_1d(w, data, None)
+        })
+    }
+
+    /// Constructs 2D matrix view of the given row-major data.
+    pub fn new_2d(data: &'a mut [f32], w: i32, h: i32) -> anyhow::Result<Self> {
+        Self::new(data, 2, [w, h, 1, 1], |data| unsafe {
+            Mat::new_external_2d(w, h, data, None)
+        })
+    }
+
+    /// Constructs 3D matrix view of the given channel-major data.
+    ///
+    /// Each channel must start at a multiple of ncnn `cstep`, which differs from `w * h` when
+    /// channels are not 16 bytes aligned. Use [Mat::from_vec_3d] to let the data be padded.
+    pub fn new_3d(data: &'a mut [f32], w: i32, h: i32, c: i32) -> anyhow::Result<Self> {
+        Self::new(data, 3, [w, h, 1, c], |data| unsafe {
+            Mat::new_external_3d(w, h, c, data, None)
+        })
+    }
+
+    /// Constructs 4D matrix view of the given channel-major data.
+    ///
+    /// Each channel must start at a multiple of ncnn `cstep`, see [MatView::new_3d].
+    pub fn new_4d(data: &'a mut [f32], w: i32, h: i32, d: i32, c: i32) -> anyhow::Result<Self> {
+        Self::new(data, 4, [w, h, d, c], |data| unsafe {
+            Mat::new_external_4d(w, h, d, c, data, None)
+        })
+    }
+
+    fn new(
+        data: &'a mut [f32],
+        dims: i32,
+        shape: [i32; 4],
+        create: impl FnOnce(*mut c_void) -> Mat,
+    ) -> anyhow::Result<Self> {
+        let (_, cstep, channels) = external_layout(dims, shape)?;
+        if data.len() != cstep * channels {
+            anyhow::bail!(
+                "Expected data length {} (cstep {}), provided {}",
+                cstep * channels,
+                cstep,
+                data.len()
+            );
+        }
+
+        Ok(Self {
+            mat: create(data.as_mut_ptr() as *mut c_void),
+            _phantom: PhantomData,
+        })
+    }
+}
+
+// Mutable access is not provided, swapping the inner matrix out would detach it from `'a`.
+impl<'a> Deref for MatView<'a> {
+    type Target = Mat;
+
+    fn deref(&self) -> &Mat {
+        &self.mat
+    }
+}
+
+impl<'a> fmt::Debug for MatView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.mat.fmt(f)
+    }
+}
+
+/// Fails unless `data` holds `height` rows of `width` pixels, each row `stride` bytes apart.
+fn check_pixels(
+    data: &[u8],
+    pixel_type: &MatPixelType,
+    width: i32,
+    height: i32,
+    stride: i32,
+) -> anyhow::Result<()> {
+    if width <= 0 || height <= 0 {
+        anyhow::bail!("Invalid image size {}x{}", width, height);
+    }
+    let row = width
+        .checked_mul(pixel_type.stride())
+        .ok_or_else(|| anyhow::anyhow!("Image width {} is too large", width))?;
+    if stride < row {
+        anyhow::bail!("Stride {} is shorter than a row of {} bytes", stride, row);
+    }
+    let len = (stride as usize)
+        .checked_mul((height - 1) as usize)
+        .and_then(|v| v.checked_add(row as usize))
+        .ok_or_else(|| anyhow::anyhow!("Image size {}x{} is too large", width, height))?;
+    if data.len() < len {
+        anyhow::bail!(
+            "Expected data length at least {}, provided {}",
+            len,
+            data.len()
+        );
+    }
+    Ok(())
+}
+
+/// Returns `(plane, cstep, channels)` of an external `f32` matrix, as computed by ncnn.
+///
+/// `cstep * channels` is checked not to overflow, so callers may multiply freely.
+fn external_layout(dims: i32, shape: [i32; 4]) -> anyhow::Result<(usize, usize, usize)> {
+    if shape.iter().any(|v| *v <= 0) {
+        anyhow::bail!("Invalid matrix shape {:?}", shape);
+    }
+    let [w, h, d, c] = shape.map(|v| v as usize);
+    let too_large = || anyhow::anyhow!("Matrix shape {:?} is too large", shape);
+    let plane = w
+        .checked_mul(h)
+        .and_then(|v| v.checked_mul(d))
+        .ok_or_else(too_large)?;
+    let cstep = if dims < 3 {
+        plane
+    } else {
+        // ncnn aligns each channel to 16 bytes
+        plane.div_ceil(4).checked_mul(4).ok_or_else(too_large)?
+    };
+    cstep
+        .checked_mul(c)
+        .and_then(|v| v.checked_mul(std::mem::size_of::<f32>()))
+        .ok_or_else(too_large)?;
+    Ok((plane, cstep, c))
+}
+
+unsafe fn slice_or_empty<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    }
+}
+
+unsafe fn slice_or_empty_mut<'a, T>(data: *mut T, len: usize) -> &'a mut [T] {
+    if len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(data, len)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::Mat;
+    use crate::{Mat, MatPixelType, MatView, Rect};
 
     #[test]
     fn basic_getter_and_setter() {
@@ -463,4 +1251,160 @@ mod tests {
         assert_eq!(224, m.w());
         assert_eq!(3, m.c());
     }
+
+    #[test]
+    fn channel_views_skip_padding() {
+        let mut m = Mat::new_3d(3, 5, 2, None);
+        m.fill(0.0);
+        m.channel_mut::<f32>(1).unwrap().fill(2.0);
+        m[(0, 4, 2)] = 1.0;
+
+        assert_eq!(15, m.channel::<f32>(0).unwrap().len());
+        assert_eq!(1.0, m.channel::<f32>(0).unwrap()[14]);
+        assert!(m.channel::<f32>(1).unwrap().iter().all(|v| *v == 2.0));
+        assert_eq!(2.0, m[(1, 0, 0)]);
+        assert!(m.channel::<f32>(2).is_err());
+        assert!(m.channel::<u8>(0).is_err());
+    }
+
+    #[test]
+    fn row_and_slice_of_2d() {
+        let mut m = Mat::new_2d(4, 3, None);
+        m.as_mut_slice::<f32>()
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, v)| *v = i as f32);
+
+        assert_eq!(&[4.0, 5.0, 6.0, 7.0], m.row::<f32>(1).unwrap());
+        assert_eq!(11.0, m[(2, 3)]);
+        assert!(m.row::<f32>(3).is_err());
+    }
+
+    #[test]
+    fn pixels_round_trip() {
+        let pixels = (0..4 * 2 * 3).map(|v| v as u8).collect::<Vec<_>>();
+        let m = Mat::from_pixels(&pixels, MatPixelType::RGB, 4, 2, None).unwrap();
+
+        let mut out = vec![0; pixels.len()];
+        m.to_pixels(&mut out, MatPixelType::RGBtoBGR).unwrap();
+        assert_eq!(&[2, 1, 0], &out[..3]);
+
+        let mut resized = vec![0; 2 * 4];
+        m.to_pixels_resize(&mut resized, MatPixelType::RGBtoRGBA, 2, 1)
+            .unwrap();
+        assert!(m.to_pixels(&mut out, MatPixelType::RGBtoGRAY).is_err());
+        assert!(m.to_pixels(&mut out[1..], MatPixelType::RGB).is_err());
+
+        // -2x-1 RGBA and a 2^30 + 2 wide row both multiply to 8 bytes in i32
+        let (w, h) = ((1 << 30) + 2, 1);
+        assert!(m
+            .to_pixels_resize(&mut resized, MatPixelType::RGBtoRGBA, -2, -1)
+            .is_err());
+        assert!(m
+            .to_pixels_resize(&mut resized, MatPixelType::RGBtoRGBA, w, h)
+            .is_err());
+    }
+
+    #[test]
+    fn pixels_with_padded_rows() {
+        // 2 rows of 3 RGB pixels padded to 12 bytes, last row unpadded
+        let pixels = vec![0; 12 + 9];
+        let m = Mat::from_pixels_stride(&pixels, MatPixelType::RGB, 3, 2, 12, None).unwrap();
+        assert_eq!((3, 2, 3), (m.w(), m.h(), m.c()));
+
+        let m = Mat::from_pixels_resize_stride(&pixels, MatPixelType::RGB, 3, 2, 12, 6, 4, None)
+            .unwrap();
+        assert_eq!((6, 4), (m.w(), m.h()));
+
+        assert!(Mat::from_pixels_stride(&pixels, MatPixelType::RGB, 3, 2, 8, None).is_err());
+        assert!(Mat::from_pixels_stride(&pixels[1..], MatPixelType::RGB, 3, 2, 12, None).is_err());
+    }
+
+    #[test]
+    fn pixels_size_overflow() {
+        // a row of 2^30 + 1 RGBA pixels wraps to 4 bytes in i32
+        let pixels = vec![0; 16];
+        let width = (1 << 30) + 1;
+        assert!(Mat::from_pixels_stride(&pixels, MatPixelType::RGBA, width, 1, 4, None).is_err());
+        assert!(Mat::from_pixels_resize_stride(
+            &pixels,
+            MatPixelType::RGBA,
+            width,
+            1,
+            4,
+            1,
+            1,
+            None
+        )
+        .is_err());
+        assert!(
+            Mat::from_pixels_stride(&pixels, MatPixelType::GRAY, 1, i32::MAX, i32::MAX, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn pixels_roi_checks_bounds() {
+        // 3 rows of 4 gray pixels padded to 8 bytes, last row unpadded
+        let pixels = vec![1; 8 * 2 + 4];
+        let m = Mat::from_pixels_roi(
+            &pixels,
+            MatPixelType::GRAY,
+            4,
+            3,
+            8,
+            Rect::new(1, 1, 2, 2),
+            None,
+        )
+        .unwrap();
+        assert_eq!((2, 2, 1), (m.w(), m.h(), m.c()));
+
+        let m = Mat::from_pixels_roi_resize(
+            &pixels,
+            MatPixelType::GRAY,
+            4,
+            3,
+            8,
+            Rect::new(0, 0, 4, 3),
+            6,
+            6,
+            None,
+        )
+        .unwrap();
+        assert_eq!((6, 6), (m.w(), m.h()));
+
+        let roi = Rect::new(2, 2, 3, 1);
+        assert!(Mat::from_pixels_roi(&pixels, MatPixelType::GRAY, 4, 3, 8, roi, None).is_err());
+        let roi = Rect::new(1, 0, i32::MAX, 1);
+        assert!(Mat::from_pixels_roi(&pixels, MatPixelType::GRAY, 4, 3, 8, roi, None).is_err());
+        let roi = Rect::new(0, 0, 1, 1);
+        assert!(Mat::from_pixels_roi(&pixels, MatPixelType::GRAY, 4, 3, 3, roi, None).is_err());
+        assert!(
+            Mat::from_pixels_roi(&pixels[1..], MatPixelType::GRAY, 4, 3, 8, roi, None).is_err()
+        );
+    }
+
+    #[test]
+    fn from_vec_pads_channels() {
+        let data = (0..30).map(|v| v as f32).collect::<Vec<_>>();
+        let m = Mat::from_vec_3d(data, 3, 5, 2).unwrap();
+        assert_eq!(16, m.cstep());
+        assert_eq!(15.0, m.channel::<f32>(1).unwrap()[0]);
+        assert!(Mat::from_vec_2d(vec![0.0; 5], 3, 2).is_err());
+        let max = i32::MAX;
+        assert!(Mat::from_vec_4d(Vec::new(), max, max, max, max).is_err());
+    }
+
+    #[test]
+    fn mat_view_borrows_slice() {
+        let mut data = vec![1.0; 2 * 16];
+        let view = MatView::new_3d(&mut data, 4, 4, 2).unwrap();
+        assert_eq!(2, view.c());
+        assert_eq!(1.0, view[(1, 3, 3)]);
+        drop(view);
+
+        let mut unaligned = vec![1.0; 2 * 15];
+        assert!(MatView::new_3d(&mut unaligned, 3, 5, 2).is_err());
+    }
 }
@@ -0,0 +1,183 @@
+use crate::allocator::Allocator;
+use crate::mat::{Mat, MatPixelType};
+use image::{DynamicImage, ImageBuffer, Luma, Pixel, Rgb, RgbImage, Rgba};
+use std::borrow::Cow;
+use std::ops::Deref;
+
+/// 8-bit [image] pixel formats that ncnn reads directly.
+pub trait ImagePixel: Pixel<Subpixel = u8> {
+    /// Pixel type of an image made of these pixels.
+    fn pixel_type() -> MatPixelType;
+}
+
+impl ImagePixel for Rgb<u8> {
+    fn pixel_type() -> MatPixelType {
+        MatPixelType::RGB
+    }
+}
+
+impl ImagePixel for Rgba<u8> {
+    fn pixel_type() -> MatPixelType {
+        MatPixelType::RGBA
+    }
+}
+
+impl ImagePixel for Luma<u8> {
+    fn pixel_type() -> MatPixelType {
+        MatPixelType::GRAY
+    }
+}
+
+impl Mat {
+    /// Constructs matrix from an RGB, RGBA or gray image, keeping its channels.
+    pub fn from_image<P, C>(
+        image: &ImageBuffer<P, C>,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat>
+    where
+        P: ImagePixel,
+        C: Deref<Target = [u8]>,
+    {
+        Mat::from_pixels(
+            image_pixels(image),
+            P::pixel_type(),
+            image.width() as _,
+            image.height() as _,
+            alloc,
+        )
+    }
+
+    /// Constructs matrix from resizing an RGB, RGBA or gray image, keeping its channels.
+    pub fn from_image_resize<P, C>(
+        image: &ImageBuffer<P, C>,
+        target_width: i32,
+        target_height: i32,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat>
+    where
+        P: ImagePixel,
+        C: Deref<Target = [u8]>,
+    {
+        Mat::from_pixels_resize(
+            image_pixels(image),
+            P::pixel_type(),
+            image.width() as _,
+            image.height() as _,
+            target_width,
+            target_height,
+            alloc,
+        )
+    }
+
+    /// Constructs matrix from any image.
+    ///
+    /// 8-bit RGB, RGBA and gray images are read in place, other formats are converted to
+    /// one of them first.
+    pub fn from_dynamic_image(
+        image: &DynamicImage,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        let (pixels, pixel_type) = dynamic_pixels(image);
+        Mat::from_pixels(
+            &pixels,
+            pixel_type,
+            image.width() as _,
+            image.height() as _,
+            alloc,
+        )
+    }
+
+    /// Constructs matrix from resizing any image, see [Mat::from_dynamic_image].
+    pub fn from_dynamic_image_resize(
+        image: &DynamicImage,
+        target_width: i32,
+        target_height: i32,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        let (pixels, pixel_type) = dynamic_pixels(image);
+        Mat::from_pixels_resize(
+            &pixels,
+            pixel_type,
+            image.width() as _,
+            image.height() as _,
+            target_width,
+            target_height,
+            alloc,
+        )
+    }
+
+    /// Converts 3-channel matrix in RGB order into an image.
+    pub fn to_rgb_image(&self) -> anyhow::Result<RgbImage> {
+        self.to_image_buffer::<Rgb<u8>>()
+    }
+
+    /// Converts 1, 3 or 4-channel matrix into a gray, RGB or RGBA image respectively.
+    pub fn to_image(&self) -> anyhow::Result<DynamicImage> {
+        Ok(match self.c() {
+            1 => DynamicImage::ImageLuma8(self.to_image_buffer::<Luma<u8>>()?),
+            3 => DynamicImage::ImageRgb8(self.to_image_buffer::<Rgb<u8>>()?),
+            4 => DynamicImage::ImageRgba8(self.to_image_buffer::<Rgba<u8>>()?),
+            c => anyhow::bail!("Expected matrix of 1, 3 or 4 channels, got {}", c),
+        })
+    }
+
+    fn to_image_buffer<P: ImagePixel>(&self) -> anyhow::Result<ImageBuffer<P, Vec<u8>>> {
+        let channels = P::CHANNEL_COUNT as i32;
+        if self.dims() != 3 || self.c() != channels {
+            anyhow::bail!(
+                "Expected 3D matrix of {} channels, got {:?}",
+                channels,
+                self
+            );
+        }
+
+        let (width, height) = (self.w(), self.h());
+        let mut pixels = vec![0u8; (width * height * channels) as usize];
+        self.to_pixels(&mut pixels, P::pixel_type())?;
+        ImageBuffer::from_raw(width as _, height as _, pixels)
+            .ok_or_else(|| anyhow::anyhow!("Image buffer size mismatch"))
+    }
+}
+
+/// Exact pixel bytes of an image, its container may hold more.
+fn image_pixels<P, C>(image: &ImageBuffer<P, C>) -> &[u8]
+where
+    P: ImagePixel,
+    C: Deref<Target = [u8]>,
+{
+    let len = image.width() as usize * image.height() as usize * P::CHANNEL_COUNT as usize;
+    &image.as_raw()[..len]
+}
+
+fn dynamic_pixels(image: &DynamicImage) -> (Cow<'_, [u8]>, MatPixelType) {
+    match image {
+        DynamicImage::ImageLuma8(i) => (Cow::Borrowed(image_pixels(i)), MatPixelType::GRAY),
+        DynamicImage::ImageRgb8(i) => (Cow::Borrowed(image_pixels(i)), MatPixelType::RGB),
+        DynamicImage::ImageRgba8(i) => (Cow::Borrowed(image_pixels(i)), MatPixelType::RGBA),
+        i if i.color().has_alpha() => (Cow::Owned(i.to_rgba8().into_raw()), MatPixelType::RGBA),
+        i if !i.color().has_color() => (Cow::Owned(i.to_luma8().into_raw()), MatPixelType::GRAY),
+        i => (Cow::Owned(i.to_rgb8().into_raw()), MatPixelType::RGB),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Mat;
+    use image::{DynamicImage, GrayImage, RgbImage};
+
+    #[test]
+    fn rgb_image_round_trip() {
+        let image = RgbImage::from_fn(4, 3, |x, y| image::Rgb([x as u8, y as u8, 7]));
+        let mat = Mat::from_image(&image, None).unwrap();
+        assert_eq!((4, 3, 3), (mat.w(), mat.h(), mat.c()));
+        assert_eq!(image, mat.to_rgb_image().unwrap());
+    }
+
+    #[test]
+    fn dynamic_image_keeps_gray() {
+        let image = DynamicImage::ImageLuma8(GrayImage::new(8, 8));
+        let mat = Mat::from_dynamic_image_resize(&image, 4, 4, None).unwrap();
+        assert_eq!((4, 4, 1), (mat.w(), mat.h(), mat.c()));
+        assert!(mat.to_rgb_image().is_err());
+    }
+}
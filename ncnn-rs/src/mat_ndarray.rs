@@ -0,0 +1,148 @@
+use crate::allocator::Allocator;
+use crate::mat::Mat;
+use ndarray::{
+    Array, ArrayBase, ArrayView, ArrayViewMut, Axis, Data, Dimension, IxDyn, ShapeBuilder,
+};
+
+impl Mat {
+    /// Returns a zero-copy [ndarray] view of the matrix, `cstep` padding is skipped by strides.
+    ///
+    /// Axes are ordered `[w]`, `[h, w]`, `[c, h, w]` or `[c, d, h, w]` depending on dims.
+    /// Packed matrices (`elempack > 1`) cannot be viewed, use [Mat::to_array] instead.
+    pub fn as_array<D: Dimension>(&self) -> anyhow::Result<ArrayView<'_, f32, D>> {
+        let (shape, strides) = self.array_layout::<D>()?;
+        let view = unsafe { ArrayView::from_shape_ptr(shape.strides(strides), self.data() as _) };
+        Ok(view.into_dimensionality::<D>()?)
+    }
+
+    /// Returns a mutable zero-copy [ndarray] view of the matrix, see [Mat::as_array].
+    pub fn as_array_mut<D: Dimension>(&mut self) -> anyhow::Result<ArrayViewMut<'_, f32, D>> {
+        let (shape, strides) = self.array_layout::<D>()?;
+        let view =
+            unsafe { ArrayViewMut::from_shape_ptr(shape.strides(strides), self.data() as _) };
+        Ok(view.into_dimensionality::<D>()?)
+    }
+
+    /// Copies the matrix into an owned [ndarray] array, unpacking `elempack > 1` layouts.
+    ///
+    /// Packed axis is restored to its full length, e.g. a 3D matrix with `c = 8` and
+    /// `elempack = 4` becomes an array of shape `[32, h, w]`.
+    pub fn to_array<D: Dimension>(&self) -> anyhow::Result<Array<f32, D>> {
+        let elempack = self.check_element::<f32>()?;
+        self.check_array_dims::<D>()?;
+
+        let (w, h, d, c) = (
+            self.w() as usize,
+            self.h() as usize,
+            self.d() as usize,
+            self.c() as usize,
+        );
+        // ncnn packs the outermost axis, `step` is the distance between packed groups in scalars.
+        let (shape, step) = match self.dims() {
+            1 => (vec![w * elempack], elempack),
+            2 => (vec![h * elempack, w], w * elempack),
+            3 => (vec![c * elempack, h, w], self.cstep() * elempack),
+            _ => (vec![c * elempack, d, h, w], self.cstep() * elempack),
+        };
+        let inner = shape[1..].iter().product::<usize>();
+
+        let ptr = self.data() as *const f32;
+        let mut data = Vec::with_capacity(shape[0] * inner);
+        for o in 0..shape[0] {
+            let base = (o / elempack) * step + o % elempack;
+            for i in 0..inner {
+                data.push(unsafe { *ptr.add(base + i * elempack) });
+            }
+        }
+
+        let array = Array::from_shape_vec(IxDyn(&shape), data)?;
+        Ok(array.into_dimensionality::<D>()?)
+    }
+
+    /// Constructs matrix by copying an [ndarray] array of 1 to 4 dimensions.
+    ///
+    /// Axes are interpreted as in [Mat::as_array].
+    pub fn from_array<S, D>(
+        array: &ArrayBase<S, D>,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat>
+    where
+        S: Data<Elem = f32>,
+        D: Dimension,
+    {
+        let shape = array.shape().iter().map(|v| *v as i32).collect::<Vec<_>>();
+        let mut mat = match *shape.as_slice() {
+            [w] => Mat::new_1d(w, alloc),
+            [h, w] => Mat::new_2d(w, h, alloc),
+            [c, h, w] => Mat::new_3d(w, h, c, alloc),
+            [c, d, h, w] => Mat::new_4d(w, h, d, c, alloc),
+            _ => anyhow::bail!("Expected array of 1 to 4 dimensions, got {}", shape.len()),
+        };
+
+        if array.ndim() < 3 {
+            let dst = mat.as_mut_slice::<f32>()?;
+            dst.iter_mut().zip(array.iter()).for_each(|(d, s)| *d = *s);
+        } else {
+            for (c, src) in array.view().into_dyn().axis_iter(Axis(0)).enumerate() {
+                let dst = mat.channel_mut::<f32>(c as i32)?;
+                dst.iter_mut().zip(src.iter()).for_each(|(d, s)| *d = *s);
+            }
+        }
+        Ok(mat)
+    }
+
+    fn check_array_dims<D: Dimension>(&self) -> anyhow::Result<()> {
+        let dims = self.dims() as usize;
+        if self.is_empty() || !(1..=4).contains(&dims) {
+            anyhow::bail!("Cannot convert empty matrix to array");
+        }
+        if let Some(ndim) = D::NDIM {
+            if ndim != dims {
+                anyhow::bail!("Expected {}D matrix, got {}D", ndim, dims);
+            }
+        }
+        Ok(())
+    }
+
+    fn array_layout<D: Dimension>(&self) -> anyhow::Result<(IxDyn, IxDyn)> {
+        if self.check_element::<f32>()? != 1 {
+            anyhow::bail!(
+                "Cannot view packed matrix (elempack {}), use `to_array` instead",
+                self.elempack()
+            );
+        }
+        self.check_array_dims::<D>()?;
+
+        let (w, h, d, c) = (
+            self.w() as usize,
+            self.h() as usize,
+            self.d() as usize,
+            self.c() as usize,
+        );
+        let cstep = self.cstep();
+        Ok(match self.dims() {
+            1 => (IxDyn(&[w]), IxDyn(&[1])),
+            2 => (IxDyn(&[h, w]), IxDyn(&[w, 1])),
+            3 => (IxDyn(&[c, h, w]), IxDyn(&[cstep, w, 1])),
+            _ => (IxDyn(&[c, d, h, w]), IxDyn(&[cstep, h * w, w, 1])),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Mat;
+    use ndarray::{Array3, Ix2, Ix3};
+
+    #[test]
+    fn array_round_trip_skips_padding() {
+        let array = Array3::from_shape_fn((2, 5, 3), |(c, y, x)| (c * 100 + y * 10 + x) as f32);
+        let mat = Mat::from_array(&array, None).unwrap();
+        assert_eq!(16, mat.cstep());
+
+        let view = mat.as_array::<Ix3>().unwrap();
+        assert_eq!(array, view);
+        assert_eq!(array, mat.to_array::<Ix3>().unwrap());
+        assert!(mat.as_array::<Ix2>().is_err());
+    }
+}
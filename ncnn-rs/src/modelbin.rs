@@ -0,0 +1,148 @@
+use crate::datareader::DataReader;
+use crate::mat::Mat;
+use ncnn_bind::*;
+use std::marker::PhantomData;
+
+/// Layer weights, see [Layer::load_model](crate::Layer::load_model) and
+/// [CustomLayer::load_model](crate::CustomLayer::load_model).
+///
+/// Weights are read in the order layers load them, e.g. weights then bias for convolutions.
+pub struct ModelBin<'a> {
+    ptr: ncnn_modelbin_t,
+    _phantom: PhantomData<&'a ()>,
+}
+
+// Not Send, model bins over a data reader call back into its source, which may be
+// bound to the thread it was created on.
+
+impl ModelBin<'static> {
+    /// Serves the given matrices one per load call, e.g. synthetic weights for testing.
+    /// Loads past the end or of another size than the next matrix fail.
+    pub fn from_mat_array(weights: Vec<Mat>) -> Self {
+        // Handing over to ncnn clones Rust backed data, the rest is refcounted by ncnn.
+        let weights = weights.into_iter().map(Mat::into_raw).collect::<Vec<_>>();
+        let ptr = unsafe {
+            ncnn_ext_modelbin_create_from_mat_array(weights.as_ptr(), weights.len() as i32)
+        };
+        for weight in weights {
+            unsafe { ncnn_mat_destroy(weight) };
+        }
+        Self {
+            ptr,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> ModelBin<'a> {
+    /// Reads weights in the `.bin` format from a data reader.
+    pub fn from_datareader(dr: &'a DataReader) -> Self {
+        Self {
+            ptr: unsafe { ncnn_modelbin_create_from_datareader(dr.ptr()) },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Borrows a model bin passed in by ncnn, it must not be dropped.
+    pub(crate) unsafe fn from_ptr(ptr: ncnn_modelbin_t) -> Self {
+        Self {
+            ptr,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Reads `w` weights.
+    ///
+    /// `weight_type` 0 reads the `.bin` tagged format of float32, float16 or quantized
+    /// weights, 1 reads raw float32 data.
+    pub fn load_1d(&self, w: i32, weight_type: i32) -> anyhow::Result<Mat> {
+        let f = unsafe { (*self.ptr).load_1d }.expect("model bin without load_1d");
+        Self::loaded(unsafe { f(self.ptr, w, weight_type) })
+    }
+
+    /// Reads `w * h` weights as a 2D matrix, see [ModelBin::load_1d].
+    pub fn load_2d(&self, w: i32, h: i32, weight_type: i32) -> anyhow::Result<Mat> {
+        let f = unsafe { (*self.ptr).load_2d }.expect("model bin without load_2d");
+        Self::loaded(unsafe { f(self.ptr, w, h, weight_type) })
+    }
+
+    /// Reads `w * h * c` weights as a 3D matrix, see [ModelBin::load_1d].
+    pub fn load_3d(&self, w: i32, h: i32, c: i32, weight_type: i32) -> anyhow::Result<Mat> {
+        let f = unsafe { (*self.ptr).load_3d }.expect("model bin without load_3d");
+        Self::loaded(unsafe { f(self.ptr, w, h, c, weight_type) })
+    }
+
+    fn loaded(ptr: ncnn_mat_t) -> anyhow::Result<Mat> {
+        let mat = unsafe { Mat::from_ptr(ptr) };
+        if mat.is_empty() {
+            anyhow::bail!("Error loading weights");
+        }
+        Ok(mat)
+    }
+
+    pub(crate) fn ptr(&self) -> ncnn_modelbin_t {
+        self.ptr
+    }
+}
+
+impl<'a> Drop for ModelBin<'a> {
+    fn drop(&mut self) {
+        unsafe { ncnn_modelbin_destroy(self.ptr) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DataReader, Layer, Mat, ModelBin, Option, ParamDict};
+
+    #[test]
+    fn synthetic_inner_product_weights() {
+        let opt = Option::new();
+        let mut layer = Layer::new("InnerProduct").unwrap();
+        layer
+            .load_param(&"0=2 1=1 2=4".parse::<ParamDict>().unwrap())
+            .unwrap();
+        let weights = vec![
+            Mat::from_vec_1d(vec![1.0, 0.0, 0.0, 1.0], 4).unwrap(),
+            Mat::from_vec_1d(vec![0.5, -0.5], 2).unwrap(),
+        ];
+        layer
+            .load_model(&ModelBin::from_mat_array(weights))
+            .unwrap();
+        layer.create_pipeline(&opt).unwrap();
+
+        let input = Mat::from_vec_1d(vec![2.0, 3.0], 2).unwrap();
+        let mut output = Mat::new();
+        layer.forward(&input, &mut output, &opt).unwrap();
+        assert_eq!(&[2.5, 2.5], output.as_slice::<f32>().unwrap());
+    }
+
+    #[test]
+    fn synthetic_weights_size_mismatch() {
+        let mut layer = Layer::new("InnerProduct").unwrap();
+        layer
+            .load_param(&"0=2 2=4".parse::<ParamDict>().unwrap())
+            .unwrap();
+        let weights = vec![Mat::from_vec_1d(vec![1.0, 0.0], 2).unwrap()];
+        assert!(layer
+            .load_model(&ModelBin::from_mat_array(weights))
+            .is_err());
+
+        let mb = ModelBin::from_mat_array(vec![Mat::from_vec_1d(vec![1.0, 2.0], 2).unwrap()]);
+        assert!(mb.load_1d(3, 0).is_err());
+        assert!(mb.load_2d(1, 2, 0).is_ok());
+    }
+
+    #[test]
+    fn read_raw_weights() {
+        let bytes = [1.0f32, 2.0, 3.0]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let dr = DataReader::from_read(&bytes[..]);
+        let mb = ModelBin::from_datareader(&dr);
+        let weights = mb.load_1d(3, 1).unwrap();
+        assert_eq!(&[1.0, 2.0, 3.0], weights.as_slice::<f32>().unwrap());
+        assert!(mb.load_1d(1, 1).is_err());
+    }
+}
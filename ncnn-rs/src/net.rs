@@ -1,10 +1,31 @@
+use crate::custom_layer::{self, CustomLayer};
 use crate::datareader::DataReader;
-use crate::Extractor;
+use crate::mat::{Mat, Shape};
+use crate::param::{self, Graph, ParamValue};
+use crate::{Allocator, Extractor};
 use ncnn_bind::*;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::io::Read;
+use std::path::Path;
+
+/// Layer of a loaded [Net], see [Net::layers].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayerInfo {
+    /// Layer type, e.g. `Convolution`.
+    pub layer_type: String,
+    pub name: String,
+    /// Names of input blobs.
+    pub bottoms: Vec<String>,
+    /// Names of output blobs.
+    pub tops: Vec<String>,
+}
 
 pub struct Net {
     ptr: ncnn_net_t,
+    // Weights loaded from memory are referenced in place, so buffers must outlive ptr.
+    memory: Vec<Box<dyn AsRef<[u8]> + Send>>,
+    // ncnn keeps pointers to registered custom layer type names.
+    _layer_types: Vec<CString>,
 }
 
 unsafe impl Send for Net {}
@@ -13,6 +34,8 @@ impl Net {
     pub fn new() -> Net {
         Net {
             ptr: unsafe { ncnn_net_create() },
+            memory: Vec::new(),
+            _layer_types: Vec::new(),
         }
     }
 
@@ -22,29 +45,308 @@ impl Net {
         }
     }
 
-    pub fn load_param(&mut self, path: &str) -> anyhow::Result<()> {
-        let c_str = CString::new(path).unwrap();
-        if unsafe { ncnn_net_load_param(self.ptr, c_str.as_ptr()) } != 0 {
-            anyhow::bail!("Error loading params {}", path);
-        } else {
-            Ok(())
-        }
+    /// Registers a Rust layer for a layer type of the param, e.g. `YoloV5Focus`.
+    ///
+    /// Must be called before loading params, see [CustomLayer].
+    pub fn register_custom_layer<L: CustomLayer>(
+        &mut self,
+        layer_type: &str,
+    ) -> anyhow::Result<()> {
+        let layer_type = CString::new(layer_type)?;
+        unsafe {
+            ncnn_net_register_custom_layer_by_type(
+                self.ptr,
+                layer_type.as_ptr(),
+                Some(custom_layer::creator::<L>),
+                Some(custom_layer::destroyer::<L>),
+                core::ptr::null_mut(),
+            )
+        };
+        self._layer_types.push(layer_type);
+        Ok(())
     }
 
-    pub fn load_model(&mut self, path: &str) -> anyhow::Result<()> {
-        let c_str = CString::new(path).unwrap();
-        if unsafe { ncnn_net_load_model(self.ptr, c_str.as_ptr()) } != 0 {
-            anyhow::bail!("Error loading model {}", path);
-        } else {
-            Ok(())
-        }
+    /// Registers a Rust layer for a layer type index of binary params, see [CustomLayer].
+    pub fn register_custom_layer_index<L: CustomLayer>(&mut self, typeindex: i32) {
+        unsafe {
+            ncnn_net_register_custom_layer_by_typeindex(
+                self.ptr,
+                typeindex,
+                Some(custom_layer::creator::<L>),
+                Some(custom_layer::destroyer::<L>),
+                core::ptr::null_mut(),
+            )
+        };
+    }
+
+    pub fn load_param(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = open_file(path)?;
+        let load = || match file {
+            ModelFile::Path(c_str) => unsafe { ncnn_net_load_param(self.ptr, c_str.as_ptr()) },
+            ModelFile::Reader(dr) => unsafe { ncnn_net_load_param_datareader(self.ptr, dr.ptr()) },
+        };
+        custom_layer::run_ncnn(load, format!("Error loading params {}", path.display()))
+    }
+
+    /// Loads binary params written by `ncnn2mem`, blobs are then addressed by index.
+    pub fn load_param_bin(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = open_file(path)?;
+        let load = || match file {
+            ModelFile::Path(c_str) => unsafe { ncnn_net_load_param_bin(self.ptr, c_str.as_ptr()) },
+            ModelFile::Reader(dr) => unsafe {
+                ncnn_net_load_param_bin_datareader(self.ptr, dr.ptr())
+            },
+        };
+        custom_layer::run_ncnn(
+            load,
+            format!("Error loading binary params {}", path.display()),
+        )
+    }
+
+    pub fn load_model(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = open_file(path)?;
+        let load = || match file {
+            ModelFile::Path(c_str) => unsafe { ncnn_net_load_model(self.ptr, c_str.as_ptr()) },
+            ModelFile::Reader(dr) => unsafe { ncnn_net_load_model_datareader(self.ptr, dr.ptr()) },
+        };
+        custom_layer::run_ncnn(load, format!("Error loading model {}", path.display()))
+    }
+
+    /// Loads text params from memory, e.g. embedded with `include_str!`.
+    pub fn load_param_memory(&mut self, param: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let c_str = CString::new(param.as_ref())?;
+        custom_layer::run_ncnn(
+            || unsafe { ncnn_net_load_param_memory(self.ptr, c_str.as_ptr()) },
+            "Error loading params from memory".to_string(),
+        )
+    }
+
+    /// Loads binary params from memory, e.g. the `.mem.h` array written by `ncnn2mem`.
+    pub fn load_param_bin_memory(&mut self, param: &[u8]) -> anyhow::Result<()> {
+        let dr = DataReader::from_memory(param);
+        custom_layer::run_ncnn(
+            || unsafe { ncnn_net_load_param_bin_datareader(self.ptr, dr.ptr()) },
+            "Error loading binary params from memory".to_string(),
+        )
+    }
+
+    /// Loads model weights from memory, e.g. embedded with `include_bytes!` or downloaded
+    /// into a `Vec<u8>`.
+    ///
+    /// ncnn references aligned weights in place instead of copying them, so the net takes
+    /// ownership of `model` and keeps it alive until dropped. Loading fails when `model`
+    /// holds fewer weights than the loaded params require.
+    pub fn load_model_memory<T>(&mut self, model: T) -> anyhow::Result<()>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        // Box before borrowing, moving an array by value would invalidate references.
+        let model: Box<dyn AsRef<[u8]> + Send> = Box::new(model);
+        let dr = DataReader::from_memory((*model).as_ref());
+        let result = custom_layer::run_ncnn(
+            || unsafe { ncnn_net_load_model_datareader(self.ptr, dr.ptr()) },
+            "Error loading model from memory".to_string(),
+        );
+        drop(dr);
+        // Layers loaded before a failure may still reference the buffer.
+        self.memory.push(model);
+        result
+    }
+
+    /// Loads model weights by memory-mapping the file at `path`.
+    ///
+    /// Weights are paged in lazily and shared with other processes mapping the same file,
+    /// the mapping is kept until the net is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, until
+    /// the net is dropped. ncnn references the weights in place, so a change is undefined
+    /// behavior and truncation may crash on access.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn load_model_mmap(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .map_err(|e| anyhow::anyhow!("Error opening model {}: {}", path.display(), e))?;
+        let mmap = memmap2::Mmap::map(&file)?;
+        self.load_model_memory(mmap)
+    }
+
+    /// Loads text params streamed from `reader`, e.g. an archive entry or a decrypted stream.
+    pub fn load_param_reader(&mut self, reader: impl Read) -> anyhow::Result<()> {
+        self.load_param_datareader(&DataReader::from_read(reader))
+    }
+
+    /// Loads model weights streamed from `reader`, see [Net::load_param_reader].
+    pub fn load_model_reader(&mut self, reader: impl Read) -> anyhow::Result<()> {
+        self.load_model_datareader(&DataReader::from_read(reader))
+    }
+
+    pub fn load_param_datareader(&mut self, dr: &DataReader) -> anyhow::Result<()> {
+        custom_layer::run_ncnn(
+            || unsafe { ncnn_net_load_param_datareader(self.ptr, dr.ptr()) },
+            "Error loading params from datareader".to_string(),
+        )
     }
 
     pub fn load_model_datareader(&mut self, dr: &DataReader) -> anyhow::Result<()> {
-        if unsafe { ncnn_net_load_model_datareader(self.ptr, dr.ptr()) } != 0 {
-            anyhow::bail!("Error loading model from datareader");
-        } else {
-            Ok(())
+        custom_layer::run_ncnn(
+            || unsafe { ncnn_net_load_model_datareader(self.ptr, dr.ptr()) },
+            "Error loading model from datareader".to_string(),
+        )
+    }
+
+    /// Returns names of the network input blobs, empty for binary params.
+    pub fn input_names(&self) -> Vec<String> {
+        let count = unsafe { ncnn_net_get_input_count(self.ptr) };
+        (0..count)
+            .filter_map(|i| unsafe { c_string(ncnn_ext_net_get_input_name(self.ptr, i)) })
+            .collect()
+    }
+
+    /// Returns names of the network output blobs, empty for binary params.
+    pub fn output_names(&self) -> Vec<String> {
+        let count = unsafe { ncnn_net_get_output_count(self.ptr) };
+        (0..count)
+            .filter_map(|i| unsafe { c_string(ncnn_ext_net_get_output_name(self.ptr, i)) })
+            .collect()
+    }
+
+    /// Returns blob indexes of the network inputs, see [Extractor::input_index].
+    pub fn input_indexes(&self) -> Vec<i32> {
+        let count = unsafe { ncnn_net_get_input_count(self.ptr) };
+        (0..count)
+            .map(|i| unsafe { ncnn_net_get_input_index(self.ptr, i) })
+            .collect()
+    }
+
+    /// Returns blob indexes of the network outputs, see [Extractor::extract_index].
+    pub fn output_indexes(&self) -> Vec<i32> {
+        let count = unsafe { ncnn_net_get_output_count(self.ptr) };
+        (0..count)
+            .map(|i| unsafe { ncnn_net_get_output_index(self.ptr, i) })
+            .collect()
+    }
+
+    /// Returns name of a blob by index, empty for binary params.
+    pub fn blob_name(&self, index: i32) -> Option<String> {
+        unsafe { c_string(ncnn_ext_net_get_blob_name(self.ptr, index)) }
+    }
+
+    /// Returns `-23330` shape hint of a blob by index, `None` when the param carries none.
+    pub fn blob_shape(&self, index: i32) -> Option<Shape> {
+        let mut shape = Shape::default();
+        unsafe {
+            ncnn_ext_net_get_blob_shape(
+                self.ptr,
+                index,
+                &mut shape.dims,
+                &mut shape.w,
+                &mut shape.h,
+                &mut shape.c,
+            )
+        };
+        (shape.dims != 0).then_some(shape)
+    }
+
+    /// Returns shape hint of a network input by name.
+    pub fn input_shape(&self, name: &str) -> Option<Shape> {
+        self.blob_shape(self.input_index(name)?)
+    }
+
+    /// Allocates a matrix of the shape hinted for a network input.
+    pub fn new_input_mat(&self, name: &str, alloc: Option<&Allocator>) -> anyhow::Result<Mat> {
+        self.input_shape(name)
+            .ok_or_else(|| anyhow::anyhow!("Input `{}` has no shape hint", name))?
+            .new_mat(alloc)
+    }
+
+    /// Checks a matrix against the shape hint of a network input, to be called before
+    /// [Extractor::input]. Inputs without a hint accept any shape.
+    pub fn check_input(&self, name: &str, mat: &Mat) -> anyhow::Result<()> {
+        let index = self
+            .input_index(name)
+            .ok_or_else(|| anyhow::anyhow!("Net has no input `{}`", name))?;
+        match self.blob_shape(index) {
+            Some(shape) => shape
+                .check(mat)
+                .map_err(|e| anyhow::anyhow!("Invalid input `{}`: {}", name, e)),
+            None => Ok(()),
+        }
+    }
+
+    fn input_index(&self, name: &str) -> Option<i32> {
+        let count = unsafe { ncnn_net_get_input_count(self.ptr) };
+        (0..count).find_map(|i| unsafe {
+            let input = c_string(ncnn_ext_net_get_input_name(self.ptr, i))?;
+            (input == name).then(|| ncnn_net_get_input_index(self.ptr, i))
+        })
+    }
+
+    /// Iterates over layers in param order.
+    pub fn layers(&self) -> impl Iterator<Item = LayerInfo> + '_ {
+        let count = unsafe { ncnn_ext_net_get_layer_count(self.ptr) };
+        (0..count).map(move |i| unsafe {
+            let bottoms = (0..ncnn_ext_net_get_layer_bottom_count(self.ptr, i))
+                .map(|j| ncnn_ext_net_get_layer_bottom(self.ptr, i, j));
+            let tops = (0..ncnn_ext_net_get_layer_top_count(self.ptr, i))
+                .map(|j| ncnn_ext_net_get_layer_top(self.ptr, i, j));
+            LayerInfo {
+                layer_type: c_string(ncnn_ext_net_get_layer_type(self.ptr, i)).unwrap_or_default(),
+                name: c_string(ncnn_ext_net_get_layer_name(self.ptr, i)).unwrap_or_default(),
+                bottoms: bottoms
+                    .map(|b| self.blob_name(b).unwrap_or_default())
+                    .collect(),
+                tops: tops
+                    .map(|t| self.blob_name(t).unwrap_or_default())
+                    .collect(),
+            }
+        })
+    }
+
+    /// Rebuilds the loaded network as a [Graph], e.g. for [Graph::to_dot].
+    ///
+    /// ncnn keeps no layer params after loading, so layers carry only `-23330` shape hints.
+    /// Unnamed blobs and layers of binary params are named by their index.
+    pub fn graph(&self) -> Graph {
+        let blob = |b: i32| {
+            self.blob_name(b)
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| b.to_string())
+        };
+        let count = unsafe { ncnn_ext_net_get_layer_count(self.ptr) };
+        let layers = (0..count).map(|i| unsafe {
+            let bottoms = (0..ncnn_ext_net_get_layer_bottom_count(self.ptr, i))
+                .map(|j| ncnn_ext_net_get_layer_bottom(self.ptr, i, j))
+                .collect::<Vec<_>>();
+            let tops = (0..ncnn_ext_net_get_layer_top_count(self.ptr, i))
+                .map(|j| ncnn_ext_net_get_layer_top(self.ptr, i, j))
+                .collect::<Vec<_>>();
+
+            let shapes = tops.iter().map(|t| self.blob_shape(*t));
+            let mut params = Vec::new();
+            if shapes.clone().any(|s| s.is_some()) {
+                let hints = shapes
+                    .map(Option::unwrap_or_default)
+                    .flat_map(|s| [s.dims, s.w, s.h, s.c])
+                    .collect();
+                params.push((param::SHAPE_HINTS_ID, ParamValue::IntArray(hints)));
+            }
+
+            let name = c_string(ncnn_ext_net_get_layer_name(self.ptr, i)).unwrap_or_default();
+            param::Layer {
+                layer_type: c_string(ncnn_ext_net_get_layer_type(self.ptr, i)).unwrap_or_default(),
+                name: if name.is_empty() { i.to_string() } else { name },
+                bottoms: bottoms.into_iter().map(blob).collect(),
+                tops: tops.into_iter().map(blob).collect(),
+                params,
+            }
+        });
+        Graph {
+            layers: layers.collect(),
         }
     }
 
@@ -65,6 +367,35 @@ impl Drop for Net {
     }
 }
 
+unsafe fn c_string(ptr: *const std::os::raw::c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+/// File for ncnn to open by path, or opened in Rust when ncnn cannot open the path.
+enum ModelFile {
+    Path(CString),
+    #[cfg_attr(unix, allow(dead_code))]
+    Reader(DataReader<'static>),
+}
+
+#[cfg(unix)]
+fn open_file(path: &Path) -> anyhow::Result<ModelFile> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(ModelFile::Path(CString::new(path.as_os_str().as_bytes())?))
+}
+
+// ncnn opens files with fopen, which takes narrow strings in the ANSI code page on
+// other platforms, so only ASCII paths are passed through.
+#[cfg(not(unix))]
+fn open_file(path: &Path) -> anyhow::Result<ModelFile> {
+    if let Some(path) = path.to_str().filter(|p| p.is_ascii()) {
+        return Ok(ModelFile::Path(CString::new(path)?));
+    }
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Error opening {}: {}", path.display(), e))?;
+    Ok(ModelFile::Reader(DataReader::from_read(file)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,6 +414,159 @@ mod tests {
             .expect_err("Expected param to be not found");
     }
 
+    #[test]
+    fn load_not_exist_param_bin() {
+        let mut net = Net::new();
+        net.load_param_bin("not_exist.param.bin")
+            .expect_err("Expected param to be not found");
+    }
+
+    #[test]
+    fn introspect_layers() {
+        let mut net = Net::new();
+        net.load_param_memory(
+            "7767517\n3 4\n\
+             Input data 0 1 data\n\
+             ReLU relu 1 1 data relu\n\
+             Split split 1 2 relu a b\n",
+        )
+        .unwrap();
+
+        assert_eq!(vec!["data"], net.input_names());
+        assert_eq!(vec!["a", "b"], net.output_names());
+        assert_eq!(vec![0], net.input_indexes());
+        assert_eq!(Some("relu".to_string()), net.blob_name(1));
+        assert_eq!(None, net.blob_name(4));
+
+        let layers = net.layers().collect::<Vec<_>>();
+        assert_eq!(3, layers.len());
+        assert_eq!("ReLU", layers[1].layer_type);
+        assert_eq!(vec!["data"], layers[1].bottoms);
+        assert_eq!(vec!["a", "b"], layers[2].tops);
+    }
+
+    #[test]
+    fn input_shape_hints() {
+        let mut net = Net::new();
+        net.load_param_memory(
+            "7767517\n2 2\n\
+             Input data 0 1 data -23330=4,3,8,6,3\n\
+             ReLU relu 1 1 data relu\n",
+        )
+        .unwrap();
+
+        assert_eq!(Some(Shape::new(3, 8, 6, 3)), net.input_shape("data"));
+        assert_eq!(None, net.blob_shape(1));
+
+        let mat = net.new_input_mat("data", None).unwrap();
+        assert_eq!((8, 6, 3), (mat.w(), mat.h(), mat.c()));
+        net.check_input("data", &mat).unwrap();
+        assert!(net
+            .check_input("data", &Mat::new_3d(6, 8, 3, None))
+            .is_err());
+        assert!(net.check_input("relu", &mat).is_err());
+    }
+
+    #[test]
+    fn graph_of_loaded_net() {
+        let mut net = Net::new();
+        net.load_param_memory(
+            "7767517\n2 2\n\
+             Input data 0 1 data -23330=4,3,8,6,3\n\
+             ReLU relu 1 1 data relu\n",
+        )
+        .unwrap();
+
+        let graph = net.graph();
+        assert_eq!(2, graph.layers.len());
+        assert_eq!(vec!["data", "relu"], graph.blobs());
+        assert_eq!(Some(Shape::new(3, 8, 6, 3)), graph.blob_shape("data"));
+        assert!(graph
+            .to_dot()
+            .contains("l0 -> l1 [label=\"data\\n8x6x3\"];"));
+    }
+
+    #[test]
+    fn load_not_exist_non_ascii_path() {
+        let mut net = Net::new();
+        net.load_param("模型/not_exist.param")
+            .expect_err("Expected param to be not found");
+    }
+
+    #[test]
+    fn binary_params_have_no_names() {
+        let input = crate::Layer::type_to_index("Input").unwrap();
+        // magic, 1 layer, 1 blob, Input layer with no bottoms, top 0 and no params
+        let param = [7767517, 1, 1, input, 0, 1, 0, -233]
+            .iter()
+            .flat_map(|v: &i32| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let mut net = Net::new();
+        net.load_param_bin_memory(&param).unwrap();
+        assert_eq!(vec![0], net.input_indexes());
+        assert!(net.input_names().is_empty());
+        assert!(net.output_names().is_empty());
+        assert_eq!(None, net.input_shape("data"));
+        assert_eq!(None, net.blob_name(1));
+    }
+
+    #[test]
+    fn load_path_with_nul() {
+        let mut net = Net::new();
+        net.load_model("not\0exist.bin")
+            .expect_err("Expected interior nul to be rejected");
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn load_not_exist_model_mmap() {
+        let mut net = Net::new();
+        unsafe { net.load_model_mmap("not_exist.bin") }
+            .expect_err("Expected model to be not found");
+    }
+
+    #[test]
+    fn load_param_reader_rejects_garbage() {
+        let mut net = Net::new();
+        net.load_param_reader("not a param".as_bytes())
+            .expect_err("Expected param to be rejected");
+    }
+
+    #[test]
+    fn load_param_memory_rejects_garbage() {
+        let mut net = Net::new();
+        net.load_param_memory("not a param")
+            .expect_err("Expected param to be rejected");
+        net.load_param_memory("7767517\n0 0\0")
+            .expect_err("Expected interior nul to be rejected");
+    }
+
+    #[test]
+    fn load_param_bin_memory_checks_length() {
+        let mut net = Net::new();
+        net.load_param_bin_memory(&7767517i32.to_ne_bytes())
+            .expect_err("Expected truncated params to be rejected");
+        net.load_param_bin_memory(&[])
+            .expect_err("Expected empty params to be rejected");
+    }
+
+    #[test]
+    fn load_model_memory_checks_length() {
+        let mut net = Net::new();
+        net.load_param_memory(
+            "7767517\n2 2\nInput data 0 1 data\nInnerProduct fc 1 1 data out 0=2 2=4\n",
+        )
+        .unwrap();
+        // raw float32 tag followed by 1 of the 4 weights
+        net.load_model_memory(vec![0u8; 8])
+            .expect_err("Expected short model to be rejected");
+
+        let mut net = Net::new();
+        net.load_param_memory("7767517\n2 2\nInput data 0 1 data\nReLU relu 1 1 data out\n")
+            .unwrap();
+        net.load_model_memory(vec![0u8; 4]).unwrap();
+    }
+
     #[test]
     fn check_sync_send() {
         assert!(is_send::<Net>());
@@ -34,6 +34,11 @@ impl Option {
         unsafe { ncnn_option_get_use_vulkan_compute(self.ptr) != 0 }
     }
 
+    /// Takes ownership of an option created by ncnn.
+    pub(crate) unsafe fn from_ptr(ptr: ncnn_option_t) -> Option {
+        Option { ptr }
+    }
+
     pub(crate) fn ptr(&self) -> ncnn_option_t {
         self.ptr
     }
@@ -0,0 +1,851 @@
+//! Pure Rust model of ncnn `.param` text files.
+//!
+//! A param file starts with the magic number `7767517`, followed by layer and blob counts
+//! and one line per layer:
+//!
+//! ```text
+//! Convolution conv1 1 1 data conv1 0=64 1=3 -23330=4,3,113,113,64
+//! ```
+//!
+//! i.e. type, name, bottom and top counts, bottom and top blob names and `id=value` params,
+//! where ids `-23300 - n` hold arrays for param `n`.
+
+use crate::mat::Shape;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Magic number on the first line of a `.param` file.
+pub const MAGIC: i32 = 7767517;
+
+/// Offset of array param ids, array param `n` is written as `-23300 - n`.
+pub const ARRAY_ID_OFFSET: i32 = -23300;
+
+/// ncnn keeps params in a fixed size table, ids are `0..MAX_PARAM_COUNT` after removing
+/// the array offset.
+pub const MAX_PARAM_COUNT: i32 = 32;
+
+/// Param id of `-23330` shape hints, four `dims, w, h, c` values per top blob.
+pub const SHAPE_HINTS_ID: i32 = 30;
+
+/// Value of a layer param.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    Int(i32),
+    Float(f32),
+    IntArray(Vec<i32>),
+    FloatArray(Vec<f32>),
+}
+
+impl ParamValue {
+    pub fn is_array(&self) -> bool {
+        matches!(self, ParamValue::IntArray(_) | ParamValue::FloatArray(_))
+    }
+
+    /// Returns integer value, floats are not converted.
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            ParamValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns float value, integers are converted as ncnn does for float params.
+    pub fn as_float(&self) -> Option<f32> {
+        match self {
+            ParamValue::Int(v) => Some(*v as f32),
+            ParamValue::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            ParamValue::IntArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_float_array(&self) -> Option<&[f32]> {
+        match self {
+            ParamValue::FloatArray(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Layer of a [Graph].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    /// Layer type, e.g. `Convolution`.
+    pub layer_type: String,
+    pub name: String,
+    /// Names of input blobs.
+    pub bottoms: Vec<String>,
+    /// Names of output blobs.
+    pub tops: Vec<String>,
+    /// Params in file order, keyed by param id without the array offset.
+    pub params: Vec<(i32, ParamValue)>,
+}
+
+impl Layer {
+    /// Returns param by id, arrays use the same id as scalars, e.g. `30` for `-23330`.
+    pub fn param(&self, id: i32) -> Option<&ParamValue> {
+        self.params.iter().find(|(i, _)| *i == id).map(|(_, v)| v)
+    }
+
+    /// Returns `-23330` shape hints of the tops, empty when the layer carries none.
+    pub fn shape_hints(&self) -> Vec<Shape> {
+        self.param(SHAPE_HINTS_ID)
+            .and_then(ParamValue::as_int_array)
+            .map(|hints| {
+                hints
+                    .chunks_exact(4)
+                    .map(|h| Shape::new(h[0], h[1], h[2], h[3]))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn check_param_ids(&self) -> anyhow::Result<()> {
+        if let Some((id, _)) = self
+            .params
+            .iter()
+            .find(|(id, _)| !(0..MAX_PARAM_COUNT).contains(id))
+        {
+            anyhow::bail!(
+                "Layer `{}` has param id {} out of range 0..{}",
+                self.name,
+                id,
+                MAX_PARAM_COUNT
+            );
+        }
+        Ok(())
+    }
+
+    fn parse(line: &str) -> anyhow::Result<Layer> {
+        let mut tokens = line.split_whitespace();
+        let mut next = |what: &str| {
+            tokens
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Missing {}", what))
+        };
+
+        let layer_type = next("layer type")?.to_string();
+        let name = next("layer name")?.to_string();
+        let bottom_count = parse_count(next("bottom count")?)?;
+        let top_count = parse_count(next("top count")?)?;
+        let bottoms = (0..bottom_count)
+            .map(|_| next("bottom name").map(str::to_string))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let tops = (0..top_count)
+            .map(|_| next("top name").map(str::to_string))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut params: Vec<(i32, ParamValue)> = Vec::new();
+        for token in tokens {
+            let (id, value) = parse_param(token)?;
+            if params.iter().any(|(i, _)| *i == id) {
+                anyhow::bail!("Duplicate param id {} in `{}`", id, token);
+            }
+            params.push((id, value));
+        }
+
+        Ok(Layer {
+            layer_type,
+            name,
+            bottoms,
+            tops,
+            params,
+        })
+    }
+}
+
+/// Network graph parsed from a `.param` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Graph {
+    /// Layers in file order.
+    pub layers: Vec<Layer>,
+}
+
+impl Graph {
+    /// Reads and parses a `.param` file.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Graph> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Error reading params {}: {}", path.display(), e))?;
+        text.parse()
+    }
+
+    /// Returns the number of distinct blobs, as written in the header.
+    pub fn blob_count(&self) -> usize {
+        self.blobs().len()
+    }
+
+    /// Returns distinct blob names in order of first use, which matches ncnn blob indexes.
+    pub fn blobs(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.layers
+            .iter()
+            .flat_map(|l| l.bottoms.iter().chain(&l.tops))
+            .map(String::as_str)
+            .filter(|b| seen.insert(*b))
+            .collect()
+    }
+
+    /// Returns layer by name.
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    /// Returns index of the layer producing a blob.
+    pub fn producer(&self, blob: &str) -> Option<usize> {
+        self.layers
+            .iter()
+            .position(|l| l.tops.iter().any(|t| t == blob))
+    }
+
+    /// Returns the `-23330` shape hint of a blob, as written by its producer.
+    pub fn blob_shape(&self, blob: &str) -> Option<Shape> {
+        let layer = &self.layers[self.producer(blob)?];
+        let top = layer.tops.iter().position(|t| t == blob)?;
+        layer.shape_hints().get(top).copied()
+    }
+
+    /// Returns indexes of layers consuming a blob.
+    pub fn consumers(&self, blob: &str) -> Vec<usize> {
+        (0..self.layers.len())
+            .filter(|i| self.layers[*i].bottoms.iter().any(|b| b == blob))
+            .collect()
+    }
+
+    /// Writes the graph as a `.param` file, see [Graph]'s [Display](fmt::Display).
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        for layer in &self.layers {
+            layer.check_param_ids()?;
+        }
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string())
+            .map_err(|e| anyhow::anyhow!("Error writing params {}: {}", path.display(), e))
+    }
+
+    /// Checks that layer names and produced blobs are unique, that every blob is
+    /// produced before it is consumed and that param ids are in range.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut names = HashSet::new();
+        let mut produced = HashSet::new();
+        for layer in &self.layers {
+            if !names.insert(layer.name.as_str()) {
+                anyhow::bail!("Duplicate layer name `{}`", layer.name);
+            }
+            for bottom in &layer.bottoms {
+                if !produced.contains(bottom.as_str()) {
+                    anyhow::bail!(
+                        "Layer `{}` consumes blob `{}` before it is produced",
+                        layer.name,
+                        bottom
+                    );
+                }
+            }
+            for top in &layer.tops {
+                if !produced.insert(top.as_str()) {
+                    anyhow::bail!("Blob `{}` is produced twice", top);
+                }
+            }
+            layer.check_param_ids()?;
+            if let Some(hints) = layer.param(SHAPE_HINTS_ID) {
+                let len = hints.as_int_array().map_or(0, <[i32]>::len);
+                if len != 4 * layer.tops.len() {
+                    anyhow::bail!(
+                        "Layer `{}` has {} tops but malformed shape hints",
+                        layer.name,
+                        layer.tops.len()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames a blob everywhere it is produced or consumed.
+    pub fn rename_blob(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        let blobs = self.blobs();
+        if !blobs.contains(&from) {
+            anyhow::bail!("Blob `{}` not found", from);
+        }
+        if blobs.contains(&to) {
+            anyhow::bail!("Blob `{}` already exists", to);
+        }
+
+        for layer in &mut self.layers {
+            for blob in layer.bottoms.iter_mut().chain(layer.tops.iter_mut()) {
+                if blob == from {
+                    *blob = to.to_string();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts a layer at `index`, its bottoms must be produced by earlier layers.
+    pub fn insert_layer(&mut self, index: usize, layer: Layer) -> anyhow::Result<()> {
+        if index > self.layers.len() {
+            anyhow::bail!("Layer index {} out of {} layers", index, self.layers.len());
+        }
+        let mut graph = self.clone();
+        graph.layers.insert(index, layer);
+        graph.validate()?;
+        *self = graph;
+        Ok(())
+    }
+
+    /// Removes a layer by name and returns it.
+    ///
+    /// Consumers of a single-input single-output layer are reconnected to its input,
+    /// other layers can only be removed once their outputs are unused.
+    pub fn remove_layer(&mut self, name: &str) -> anyhow::Result<Layer> {
+        let index = self
+            .layers
+            .iter()
+            .position(|l| l.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Layer `{}` not found", name))?;
+        let layer = self.layers.remove(index);
+
+        match (layer.bottoms.as_slice(), layer.tops.as_slice()) {
+            ([bottom], [top]) => {
+                for consumer in &mut self.layers[index..] {
+                    for b in consumer.bottoms.iter_mut().filter(|b| *b == top) {
+                        *b = bottom.clone();
+                    }
+                }
+            }
+            (_, tops) => {
+                if let Some(top) = tops.iter().find(|t| !self.consumers(t).is_empty()) {
+                    let layer_name = &self.layers[self.consumers(top)[0]].name;
+                    let error = anyhow::anyhow!(
+                        "Blob `{}` of layer `{}` is still consumed by `{}`",
+                        top,
+                        name,
+                        layer_name
+                    );
+                    self.layers.insert(index, layer);
+                    return Err(error);
+                }
+            }
+        }
+        Ok(layer)
+    }
+
+    /// Returns the sub-graph of layers needed to compute `blob`, e.g. a backbone of a
+    /// classifier cut at its last feature map.
+    pub fn cut_at(&self, blob: &str) -> anyhow::Result<Graph> {
+        if self.producer(blob).is_none() {
+            anyhow::bail!("Blob `{}` not found", blob);
+        }
+
+        let mut needed = HashSet::from([blob]);
+        let mut keep = vec![false; self.layers.len()];
+        for (i, layer) in self.layers.iter().enumerate().rev() {
+            if layer.tops.iter().any(|t| needed.contains(t.as_str())) {
+                keep[i] = true;
+                needed.extend(layer.bottoms.iter().map(String::as_str));
+            }
+        }
+
+        let layers = self
+            .layers
+            .iter()
+            .zip(keep)
+            .filter(|(_, keep)| *keep)
+            .map(|(layer, _)| layer.clone())
+            .collect();
+        Ok(Graph { layers })
+    }
+
+    /// Describes the graph in Graphviz DOT. Layers are labeled with type, name and key params,
+    /// edges with blob name and `-23330` shape hint; blobs nobody consumes end in plain nodes.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph ncnn {\n    node [shape=box];\n");
+        for (i, layer) in self.layers.iter().enumerate() {
+            let mut label = vec![layer.layer_type.clone(), layer.name.clone()];
+            label.extend(key_params(layer));
+            dot.push_str(&format!("    l{} [label={}];\n", i, dot_label(&label)));
+        }
+
+        for (b, blob) in self.blobs().into_iter().enumerate() {
+            let mut label = vec![blob.to_string()];
+            label.extend(self.blob_shape(blob).map(|s| s.to_string()));
+            let label = dot_label(&label);
+
+            // Graph inputs and outputs get a node of their own.
+            let producer = self.producer(blob);
+            let consumers = self.consumers(blob);
+            if producer.is_none() || consumers.is_empty() {
+                dot.push_str(&format!("    b{} [shape=plaintext label={}];\n", b, label));
+            }
+            let from = match producer {
+                Some(i) => format!("l{}", i),
+                None => format!("b{}", b),
+            };
+            if producer.is_some() && consumers.is_empty() {
+                dot.push_str(&format!("    {} -> b{};\n", from, b));
+            }
+            for c in consumers {
+                dot.push_str(&format!("    {} -> l{} [label={}];\n", from, c, label));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl FromStr for Graph {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Graph> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let mut header = |what: &str| {
+            lines
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Missing {}", what))
+        };
+
+        let (_, magic) = header("magic number")?;
+        if magic.trim().parse::<i32>().ok() != Some(MAGIC) {
+            anyhow::bail!(
+                "Invalid magic number `{}`, expected {}",
+                magic.trim(),
+                MAGIC
+            );
+        }
+
+        let (_, counts) = header("layer and blob counts")?;
+        let counts = counts
+            .split_whitespace()
+            .map(parse_count)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (layer_count, blob_count) = match *counts.as_slice() {
+            [layer_count, blob_count] => (layer_count, blob_count),
+            _ => anyhow::bail!("Expected layer and blob counts, got {:?}", counts),
+        };
+
+        // Like ncnn, lines past the declared layer count are ignored.
+        let layers = lines
+            .take(layer_count)
+            .map(|(i, line)| {
+                Layer::parse(line).map_err(|e| anyhow::anyhow!("Line {}: {}", i + 1, e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let graph = Graph { layers };
+        if graph.layers.len() != layer_count {
+            anyhow::bail!(
+                "Header declares {} layers, found {}",
+                layer_count,
+                graph.layers.len()
+            );
+        }
+        if graph.blob_count() != blob_count {
+            anyhow::bail!(
+                "Header declares {} blobs, found {}",
+                blob_count,
+                graph.blob_count()
+            );
+        }
+        Ok(graph)
+    }
+}
+
+/// Writes the `.param` text format, aligned and with `%e` floats as ncnn tools write it.
+///
+/// Formatting fails for param ids ncnn does not accept, see [Graph::validate].
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", MAGIC)?;
+        writeln!(f, "{} {}", self.layers.len(), self.blob_count())?;
+        for layer in &self.layers {
+            writeln!(f, "{}", layer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a single `.param` line without the trailing newline.
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<24} {:<24} {} {}",
+            self.layer_type,
+            self.name,
+            self.bottoms.len(),
+            self.tops.len()
+        )?;
+        for blob in self.bottoms.iter().chain(&self.tops) {
+            write!(f, " {}", blob)?;
+        }
+        for (id, value) in &self.params {
+            write_param(f, *id, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes ` id=value` in `.param` notation, floats always carry an exponent so ncnn
+/// reads them back as floats.
+pub(crate) fn write_param(f: &mut impl fmt::Write, id: i32, value: &ParamValue) -> fmt::Result {
+    if !(0..MAX_PARAM_COUNT).contains(&id) {
+        return Err(fmt::Error);
+    }
+    match value {
+        ParamValue::Int(v) => write!(f, " {}={}", id, v),
+        ParamValue::Float(v) => write!(f, " {}={}", id, c_float(*v)),
+        ParamValue::IntArray(v) => {
+            write!(f, " {}={}", ARRAY_ID_OFFSET - id, v.len())?;
+            v.iter().try_for_each(|v| write!(f, ",{}", v))
+        }
+        ParamValue::FloatArray(v) => {
+            write!(f, " {}={}", ARRAY_ID_OFFSET - id, v.len())?;
+            v.iter().try_for_each(|v| write!(f, ",{}", c_float(*v)))
+        }
+    }
+}
+
+/// Output count, kernel size and stride of convolution, pooling and inner product layers.
+fn key_params(layer: &Layer) -> Vec<String> {
+    let int = |id| layer.param(id).and_then(ParamValue::as_int);
+    // Height params follow width ones by 10 and default to them.
+    let pair = |id| int(id).map(|w| format!("{}x{}", w, int(id + 10).unwrap_or(w)));
+
+    let conv = matches!(
+        layer.layer_type.as_str(),
+        "Convolution" | "ConvolutionDepthWise" | "Deconvolution" | "DeconvolutionDepthWise"
+    );
+    let mut params = Vec::new();
+    if conv || layer.layer_type == "InnerProduct" {
+        params.extend(int(0).map(|n| format!("num_output={}", n)));
+    }
+    let (kernel, stride) = match layer.layer_type.as_str() {
+        _ if conv => (1, 3),
+        "Pooling" if int(4) == Some(1) => {
+            params.push("global".to_string());
+            return params;
+        }
+        "Pooling" => (1, 2),
+        _ => return params,
+    };
+    params.extend(pair(kernel).map(|k| format!("kernel={}", k)));
+    params.extend(pair(stride).map(|s| format!("stride={}", s)));
+    params
+}
+
+/// Quotes label lines for DOT, lines are centered.
+fn dot_label(lines: &[String]) -> String {
+    let lines = lines
+        .iter()
+        .map(|l| l.replace('\\', "\\\\").replace('"', "\\\""))
+        .collect::<Vec<_>>();
+    format!("\"{}\"", lines.join("\\n"))
+}
+
+/// Formats a float like C `%e`, e.g. `1.000000e-01`.
+fn c_float(v: f32) -> String {
+    if !v.is_finite() {
+        return if v.is_nan() {
+            "nan"
+        } else if v > 0.0 {
+            "inf"
+        } else {
+            "-inf"
+        }
+        .to_string();
+    }
+    let s = format!("{:.6e}", v);
+    let (mantissa, exponent) = s.split_once('e').unwrap_or((&s, "0"));
+    let exponent = exponent.parse::<i32>().unwrap_or_default();
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+fn parse_count(token: &str) -> anyhow::Result<usize> {
+    token
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid count `{}`", token))
+}
+
+pub(crate) fn parse_param(token: &str) -> anyhow::Result<(i32, ParamValue)> {
+    let (id, value) = token
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected `id=value`, got `{}`", token))?;
+    let id = id
+        .parse::<i32>()
+        .map_err(|_| anyhow::anyhow!("Invalid param id in `{}`", token))?;
+
+    let check_id = |id: i32| {
+        if !(0..MAX_PARAM_COUNT).contains(&id) {
+            anyhow::bail!("Param id out of range in `{}`", token);
+        }
+        Ok(id)
+    };
+    if id > ARRAY_ID_OFFSET {
+        return Ok((check_id(id)?, parse_scalar(value)?));
+    }
+
+    let mut items = value.split(',');
+    let len = items.next().unwrap_or_default();
+    let len = parse_count(len)?;
+    let items = items.collect::<Vec<_>>();
+    if items.len() != len {
+        anyhow::bail!(
+            "Array `{}` declares {} items, found {}",
+            token,
+            len,
+            items.len()
+        );
+    }
+
+    let id = check_id(ARRAY_ID_OFFSET - id)?;
+    if items.iter().any(|v| is_float(v)) {
+        let items = items
+            .iter()
+            .map(|v| parse_float(v))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((id, ParamValue::FloatArray(items)))
+    } else {
+        let items = items
+            .iter()
+            .map(|v| parse_int(v))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((id, ParamValue::IntArray(items)))
+    }
+}
+
+/// ncnn treats any value containing `.`, `e` or `E` as float.
+fn is_float(value: &str) -> bool {
+    value.contains(['.', 'e', 'E'])
+}
+
+fn parse_int(value: &str) -> anyhow::Result<i32> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid integer `{}`", value))
+}
+
+fn parse_float(value: &str) -> anyhow::Result<f32> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid float `{}`", value))
+}
+
+fn parse_scalar(value: &str) -> anyhow::Result<ParamValue> {
+    if is_float(value) {
+        Ok(ParamValue::Float(parse_float(value)?))
+    } else {
+        Ok(ParamValue::Int(parse_int(value)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_export() {
+        let graph: Graph = "7767517\n3 3\n\
+                            Input data 0 1 data -23330=4,3,227,227,3\n\
+                            Convolution conv 1 1 data conv 0=64 1=3 3=2 13=1\n\
+                            Pooling pool 1 1 conv pool 0=1 4=1\n"
+            .parse()
+            .unwrap();
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph ncnn {\n"));
+        assert!(dot.contains(
+            "l1 [label=\"Convolution\\nconv\\nnum_output=64\\nkernel=3x3\\nstride=2x1\"];"
+        ));
+        assert!(dot.contains("l2 [label=\"Pooling\\npool\\nglobal\"];"));
+        assert!(dot.contains("l0 -> l1 [label=\"data\\n227x227x3\"];"));
+        assert!(dot.contains("b2 [shape=plaintext label=\"pool\"];\n    l2 -> b2;"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn dot_export_dangling_blobs() {
+        let graph: Graph = "7767517\n2 3\n\
+                            Input data 0 1 data\n\
+                            Concat cat 2 1 data extra out\n"
+            .parse()
+            .unwrap();
+        let dot = graph.to_dot();
+        assert_eq!(1, dot.matches("    b1 [").count());
+        assert!(
+            dot.contains("b1 [shape=plaintext label=\"extra\"];\n    b1 -> l1 [label=\"extra\"];")
+        );
+        assert_eq!(1, dot.matches("    b2 [").count());
+        assert!(dot.contains("l1 -> b2;"));
+        assert!(!dot.contains("b0"));
+        assert!(!dot.contains("-> b1"));
+    }
+
+    #[test]
+    fn shape_hints() {
+        let graph: Graph = "7767517\n3 4\n\
+                            Input data 0 1 data -23330=4,3,227,227,3\n\
+                            Split split 1 2 data a b -23330=8,3,227,227,3,1,1000,1,1\n\
+                            ReLU relu 1 1 a relu\n"
+            .parse()
+            .unwrap();
+        assert_eq!(Some(Shape::new(3, 227, 227, 3)), graph.blob_shape("data"));
+        assert_eq!(Some(Shape::new(1, 1000, 1, 1)), graph.blob_shape("b"));
+        assert_eq!(None, graph.blob_shape("relu"));
+        assert_eq!(2, graph.layer("split").unwrap().shape_hints().len());
+        graph.validate().unwrap();
+
+        let malformed: Graph = "7767517\n1 1\nInput data 0 1 data -23330=3,3,227,227\n"
+            .parse()
+            .unwrap();
+        assert!(malformed.validate().is_err());
+
+        let mut out_of_range = graph.clone();
+        out_of_range.layers[2].params.push((32, ParamValue::Int(1)));
+        assert!(out_of_range.validate().is_err());
+    }
+
+    #[test]
+    fn parse_layer_line() {
+        let graph: Graph = "7767517\n2 2\n\
+                            Input data 0 1 data -23330=4,3,227,227,3 0=227\n\
+                            Convolution conv 1 1 data conv 0=64 4=-233 5=1.5e-1 -23301=2,0.5,1\n"
+            .parse()
+            .unwrap();
+
+        let conv = graph.layer("conv").unwrap();
+        assert_eq!("Convolution", conv.layer_type);
+        assert_eq!(vec!["data"], conv.bottoms);
+        assert_eq!(vec!["conv"], conv.tops);
+        assert_eq!(Some(64), conv.param(0).and_then(ParamValue::as_int));
+        assert_eq!(Some(-233), conv.param(4).and_then(ParamValue::as_int));
+        assert_eq!(Some(0.15), conv.param(5).and_then(ParamValue::as_float));
+        assert_eq!(
+            Some(&[0.5, 1.0][..]),
+            conv.param(1).and_then(ParamValue::as_float_array)
+        );
+
+        let shape = graph.layers[0].param(30).unwrap();
+        assert_eq!(Some(&[3, 227, 227, 3][..]), shape.as_int_array());
+        assert_eq!(vec!["data", "conv"], graph.blobs());
+        assert_eq!(Some(0), graph.producer("data"));
+        assert_eq!(vec![1], graph.consumers("data"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("7767518\n0 0\n".parse::<Graph>().is_err());
+        assert!("7767517\n2 1\nInput data 0 1 data\n"
+            .parse::<Graph>()
+            .is_err());
+        assert!("7767517\n1 2\nInput data 0 1 data\n"
+            .parse::<Graph>()
+            .is_err());
+        assert!("7767517\n1 1\nInput data 0 2 data\n"
+            .parse::<Graph>()
+            .is_err());
+        assert!("7767517\n1 1\nInput data 0 1 data 0=1 0=2\n"
+            .parse::<Graph>()
+            .is_err());
+        assert!("7767517\n1 1\nInput data 0 1 data -23300=2,1\n"
+            .parse::<Graph>()
+            .is_err());
+        assert!("7767517\n1 1\nInput data 0 1 data 0=x\n"
+            .parse::<Graph>()
+            .is_err());
+        for id in ["32=1", "-1=1", "-23299=1", "-23332=1,1"] {
+            assert!(parse_param(id).is_err(), "{}", id);
+        }
+        assert_eq!(31, parse_param("-23331=1,1").unwrap().0);
+    }
+
+    #[test]
+    fn write_c_floats() {
+        assert_eq!("1.000000e-01", c_float(0.1));
+        assert_eq!("-2.500000e+02", c_float(-250.0));
+        assert_eq!("0.000000e+00", c_float(0.0));
+    }
+
+    #[test]
+    fn edit_graph() {
+        let mut graph: Graph = "7767517\n4 5\n\
+                                Input data 0 1 data\n\
+                                Convolution conv 1 1 data conv 0=8\n\
+                                ReLU relu 1 1 conv relu\n\
+                                Split split 1 2 relu a b\n"
+            .parse()
+            .unwrap();
+
+        graph.rename_blob("conv", "features").unwrap();
+        assert_eq!(vec!["features"], graph.layers[2].bottoms);
+        assert!(graph.rename_blob("relu", "data").is_err());
+
+        graph.remove_layer("relu").unwrap();
+        assert_eq!(vec!["features"], graph.layers[2].bottoms);
+        assert!(graph.remove_layer("data").is_err());
+        assert_eq!(3, graph.layers.len());
+
+        let sigmoid = Layer {
+            layer_type: "Sigmoid".to_string(),
+            name: "sigmoid".to_string(),
+            bottoms: vec!["a".to_string()],
+            tops: vec!["prob".to_string()],
+            params: vec![],
+        };
+        assert!(graph.insert_layer(1, sigmoid.clone()).is_err());
+        graph.insert_layer(3, sigmoid).unwrap();
+
+        let backbone = graph.cut_at("features").unwrap();
+        assert_eq!(vec!["data", "features"], backbone.blobs());
+        assert_eq!(backbone, backbone.to_string().parse::<Graph>().unwrap());
+    }
+
+    #[test]
+    fn write_param_ids_out_of_range() {
+        use std::fmt::Write;
+
+        let mut graph: Graph = "7767517\n1 1\nInput data 0 1 data\n".parse().unwrap();
+        graph.layers[0]
+            .params
+            .push((i32::MIN, ParamValue::IntArray(vec![1])));
+        assert!(write!(String::new(), "{}", graph.layers[0]).is_err());
+        let path = std::env::temp_dir().join("ncnn_rs_out_of_range.param");
+        assert!(graph.save(&path).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn parse_bundled_params() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params");
+        let mut count = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("param") {
+                continue;
+            }
+            let graph = Graph::from_file(&path).unwrap();
+            let has_input = graph.layers.iter().any(|l| l.layer_type == "Input");
+            assert!(has_input, "{}", path.display());
+            graph.validate().unwrap();
+
+            let text = graph.to_string();
+            assert_eq!(graph, text.parse::<Graph>().unwrap(), "{}", path.display());
+            count += 1;
+        }
+        assert!(count > 30);
+
+        // Files written by ncnn tools serialize back byte for byte.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../params/mobilenet_v2.param");
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, Graph::from_file(&path).unwrap().to_string());
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Layer and blob indices of a binary param, as generated by `ncnn2mem` into `.id.h`.
+///
+/// Binary params carry no names, inputs and outputs are addressed by blob index with
+/// [Extractor::input_index](crate::Extractor::input_index) and
+/// [Extractor::extract_index](crate::Extractor::extract_index).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParamIds {
+    layers: HashMap<String, i32>,
+    blobs: HashMap<String, i32>,
+}
+
+impl ParamIds {
+    /// Parses `const int LAYER_<name> = <index>;` and `const int BLOB_<name> = <index>;` lines.
+    pub fn parse(header: &str) -> anyhow::Result<Self> {
+        let mut ids = ParamIds::default();
+        for line in header.lines() {
+            let decl = match line.trim().strip_prefix("const int ") {
+                Some(decl) => decl,
+                None => continue,
+            };
+            let (name, value) = decl
+                .trim_end_matches(';')
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid id declaration `{}`", line))?;
+            let index = value.trim().parse::<i32>()?;
+
+            let name = name.trim();
+            if let Some(layer) = name.strip_prefix("LAYER_") {
+                ids.layers.insert(layer.to_string(), index);
+            } else if let Some(blob) = name.strip_prefix("BLOB_") {
+                ids.blobs.insert(blob.to_string(), index);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Reads and parses an `.id.h` file, see [ParamIds::parse].
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Returns index of a layer by its name in the text param.
+    pub fn layer(&self, name: &str) -> Option<i32> {
+        self.layers.get(&sanitize_name(name)).copied()
+    }
+
+    /// Returns index of a blob by its name in the text param.
+    pub fn blob(&self, name: &str) -> Option<i32> {
+        self.blobs.get(&sanitize_name(name)).copied()
+    }
+}
+
+/// `ncnn2mem` replaces every character outside `[0-9a-zA-Z]` with `_`.
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ParamIds;
+
+    #[test]
+    fn parse_id_header() {
+        let header = "namespace squeezenet_v1_1_param_id {\n\
+                      const int LAYER_data = 0;\n\
+                      const int BLOB_data = 0;\n\
+                      const int LAYER_fire2_relu_squeeze1x1 = 3;\n\
+                      const int BLOB_fire2_squeeze1x1 = 3;\n\
+                      } // namespace squeezenet_v1_1_param_id\n";
+        let ids = ParamIds::parse(header).unwrap();
+        assert_eq!(Some(0), ids.blob("data"));
+        assert_eq!(Some(3), ids.layer("fire2/relu_squeeze1x1"));
+        assert_eq!(Some(3), ids.blob("fire2/squeeze1x1"));
+        assert_eq!(None, ids.layer("prob"));
+        assert!(ParamIds::parse("const int BLOB_data = x;").is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use crate::mat::Mat;
+use crate::param::{self, ParamValue, MAX_PARAM_COUNT};
+use ncnn_bind::*;
+use std::ffi::CString;
+use std::str::FromStr;
+
+/// Layer params by id, see [Layer::load_param](crate::Layer::load_param) and
+/// [CustomLayer::load_param](crate::CustomLayer::load_param).
+///
+/// Arrays are keyed by id without the array offset, e.g. `30` for `-23330`.
+pub struct ParamDict {
+    ptr: ncnn_paramdict_t,
+}
+
+unsafe impl Send for ParamDict {}
+
+impl ParamDict {
+    /// Constructs an empty param dict, layers use defaults for missing params.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes ownership of a param dict created by ncnn.
+    pub(crate) unsafe fn from_ptr(ptr: ncnn_paramdict_t) -> Self {
+        Self { ptr }
+    }
+
+    /// Returns integer param by id, or `default` when it is missing.
+    pub fn get_int(&self, id: i32, default: i32) -> i32 {
+        if !valid_id(id) {
+            return default;
+        }
+        unsafe { ncnn_paramdict_get_int(self.ptr, id, default) }
+    }
+
+    /// Returns float param by id, or `default` when it is missing.
+    pub fn get_float(&self, id: i32, default: f32) -> f32 {
+        if !valid_id(id) {
+            return default;
+        }
+        unsafe { ncnn_paramdict_get_float(self.ptr, id, default) }
+    }
+
+    /// Returns array param by id as a 1D matrix.
+    pub fn get_array(&self, id: i32) -> Option<Mat> {
+        if !valid_id(id) {
+            return None;
+        }
+        let default = Mat::new();
+        let array = unsafe { Mat::from_ptr(ncnn_paramdict_get_array(self.ptr, id, default.ptr())) };
+        (!array.is_empty()).then_some(array)
+    }
+
+    /// Returns param by id with its type.
+    ///
+    /// Params from binary params carry no type and read as `None`, use
+    /// [ParamDict::get_int], [ParamDict::get_float] or [ParamDict::get_array] for them.
+    pub fn get(&self, id: i32) -> Option<ParamValue> {
+        if !valid_id(id) {
+            return None;
+        }
+        match unsafe { ncnn_paramdict_get_type(self.ptr, id) } {
+            2 => Some(ParamValue::Int(self.get_int(id, 0))),
+            3 => Some(ParamValue::Float(self.get_float(id, 0.0))),
+            5 => {
+                let array = self.get_array(id)?;
+                Some(ParamValue::IntArray(array.as_slice::<i32>().ok()?.to_vec()))
+            }
+            6 => {
+                let array = self.get_array(id)?;
+                Some(ParamValue::FloatArray(
+                    array.as_slice::<f32>().ok()?.to_vec(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn set_int(&mut self, id: i32, value: i32) -> anyhow::Result<()> {
+        check_id(id)?;
+        unsafe { ncnn_paramdict_set_int(self.ptr, id, value) };
+        Ok(())
+    }
+
+    pub fn set_float(&mut self, id: i32, value: f32) -> anyhow::Result<()> {
+        check_id(id)?;
+        unsafe { ncnn_paramdict_set_float(self.ptr, id, value) };
+        Ok(())
+    }
+
+    /// Sets array param by id from the `i32` scalars of a matrix.
+    pub fn set_int_array(&mut self, id: i32, value: &Mat) -> anyhow::Result<()> {
+        let array = value.as_slice::<i32>()?.to_vec();
+        self.set(id, &ParamValue::IntArray(array))
+    }
+
+    /// Sets array param by id from the `f32` scalars of a matrix.
+    pub fn set_float_array(&mut self, id: i32, value: &Mat) -> anyhow::Result<()> {
+        let array = value.as_slice::<f32>()?.to_vec();
+        self.set(id, &ParamValue::FloatArray(array))
+    }
+
+    /// Sets param by id.
+    ///
+    /// ncnn only keeps the element type of arrays parsed from text, so arrays are set by
+    /// reloading the whole dict. Untyped params of binary params keep their bits, but
+    /// [ParamDict::get] reads them as [ParamValue::Int] and [ParamValue::IntArray] afterwards.
+    pub fn set(&mut self, id: i32, value: &ParamValue) -> anyhow::Result<()> {
+        match value {
+            ParamValue::Int(v) => self.set_int(id, *v),
+            ParamValue::Float(v) => self.set_float(id, *v),
+            ParamValue::IntArray(_) | ParamValue::FloatArray(_) => {
+                check_id(id)?;
+                let mut params: Vec<_> = (0..MAX_PARAM_COUNT)
+                    .filter(|i| *i != id)
+                    .filter_map(|i| Some((i, self.get_bits(i)?)))
+                    .collect();
+                params.push((id, value.clone()));
+                self.load_params(&params)
+            }
+        }
+    }
+
+    /// Returns param by id like [ParamDict::get], untyped params as the ints of their bits.
+    fn get_bits(&self, id: i32) -> Option<ParamValue> {
+        match unsafe { ncnn_paramdict_get_type(self.ptr, id) } {
+            1 => Some(ParamValue::Int(self.get_int(id, 0))),
+            4 => {
+                let array = self.get_array(id)?;
+                Some(ParamValue::IntArray(array.as_slice::<i32>().ok()?.to_vec()))
+            }
+            _ => self.get(id),
+        }
+    }
+
+    /// Replaces all params through the ncnn text parser.
+    fn load_params(&mut self, params: &[(i32, ParamValue)]) -> anyhow::Result<()> {
+        let mut text = String::new();
+        for (id, value) in params {
+            check_id(*id)?;
+            param::write_param(&mut text, *id, value)?;
+        }
+        let text = CString::new(text)?;
+        if unsafe { ncnn_ext_paramdict_load_param(self.ptr, text.as_ptr()) } != 0 {
+            anyhow::bail!("Error loading params `{}`", text.to_string_lossy());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn ptr(&self) -> ncnn_paramdict_t {
+        self.ptr
+    }
+}
+
+impl Default for ParamDict {
+    fn default() -> Self {
+        Self {
+            ptr: unsafe { ncnn_paramdict_create() },
+        }
+    }
+}
+
+/// Collects params of a parsed `.param` line.
+impl TryFrom<&param::Layer> for ParamDict {
+    type Error = anyhow::Error;
+
+    fn try_from(layer: &param::Layer) -> anyhow::Result<Self> {
+        let mut pd = ParamDict::new();
+        pd.load_params(&layer.params)?;
+        Ok(pd)
+    }
+}
+
+/// Parses whitespace separated params in `.param` notation, e.g. `0=64 1=3 -23301=2,0.5,1`.
+impl FromStr for ParamDict {
+    type Err = anyhow::Error;
+
+    fn from_str(params: &str) -> anyhow::Result<Self> {
+        let params = params
+            .split_whitespace()
+            .map(param::parse_param)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut pd = ParamDict::new();
+        pd.load_params(&params)?;
+        Ok(pd)
+    }
+}
+
+impl Drop for ParamDict {
+    fn drop(&mut self) {
+        unsafe { ncnn_paramdict_destroy(self.ptr) };
+    }
+}
+
+fn valid_id(id: i32) -> bool {
+    (0..MAX_PARAM_COUNT).contains(&id)
+}
+
+fn check_id(id: i32) -> anyhow::Result<()> {
+    if !valid_id(id) {
+        anyhow::bail!("Param id {} is out of range 0..{}", id, MAX_PARAM_COUNT);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::param::ParamValue;
+    use crate::{Mat, ParamDict};
+
+    #[test]
+    fn typed_params() {
+        let mut pd: ParamDict = "0=64 1=1.5e-1 -23302=2,3,5".parse().unwrap();
+        assert_eq!(Some(ParamValue::Int(64)), pd.get(0));
+        assert_eq!(Some(ParamValue::Float(0.15)), pd.get(1));
+        assert_eq!(64, pd.get_int(0, 0));
+        assert_eq!(7, pd.get_int(3, 7));
+        assert_eq!(None, pd.get(3));
+
+        assert_eq!(Some(ParamValue::IntArray(vec![3, 5])), pd.get(2));
+        let array = pd.get_array(2).unwrap();
+        assert_eq!(&[3, 5], array.as_slice::<i32>().unwrap());
+
+        pd.set(4, &ParamValue::FloatArray(vec![0.5, 1.0])).unwrap();
+        assert_eq!(Some(ParamValue::FloatArray(vec![0.5, 1.0])), pd.get(4));
+        assert_eq!(Some(ParamValue::IntArray(vec![3, 5])), pd.get(2));
+        assert_eq!(Some(ParamValue::Float(0.15)), pd.get(1));
+
+        let mut ints = Mat::new_1d(2, None);
+        ints.as_mut_slice::<i32>().unwrap().copy_from_slice(&[7, 9]);
+        pd.set_int_array(5, &ints).unwrap();
+        assert_eq!(Some(ParamValue::IntArray(vec![7, 9])), pd.get(5));
+        let floats = Mat::from_vec_1d(vec![0.25], 1).unwrap();
+        pd.set_float_array(6, &floats).unwrap();
+        assert_eq!(Some(ParamValue::FloatArray(vec![0.25])), pd.get(6));
+        assert!("0=x".parse::<ParamDict>().is_err());
+        assert!(pd.set_int(32, 1).is_err());
+        assert_eq!(None, pd.get(-1));
+    }
+
+    #[test]
+    fn set_array_keeps_untyped_params() {
+        // binary params store arrays untyped, as ncnn_paramdict_set_array does
+        let mut pd = ParamDict::new();
+        let bits = Mat::from_vec_1d(vec![0.5, 1.0], 2).unwrap();
+        unsafe { ncnn_bind::ncnn_paramdict_set_array(pd.ptr(), 3, bits.ptr()) };
+        assert_eq!(None, pd.get(3));
+
+        pd.set(4, &ParamValue::IntArray(vec![1])).unwrap();
+        let array = pd.get_array(3).unwrap();
+        assert_eq!(&[0.5, 1.0], array.as_slice::<f32>().unwrap());
+        let expected = [0.5f32, 1.0].map(|v| v.to_bits() as i32).to_vec();
+        assert_eq!(Some(ParamValue::IntArray(expected)), pd.get(3));
+    }
+}
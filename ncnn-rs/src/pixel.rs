@@ -0,0 +1,426 @@
+//! Helpers working on raw 8-bit pixel buffers.
+
+use crate::allocator::Allocator;
+use crate::mat::{Mat, MatPixelType};
+use ncnn_bind::*;
+
+#[cfg(feature = "pixel-drawing")]
+pub mod draw;
+
+/// Memory layout of a YUV 4:2:0 frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvFormat {
+    /// Y plane followed by interleaved V/U samples, the Android camera default.
+    NV21,
+    /// Y plane followed by interleaved U/V samples.
+    NV12,
+    /// Y plane followed by a U plane and a V plane.
+    I420,
+}
+
+/// Converts a YUV 4:2:0 frame into packed RGB pixels.
+///
+/// `width` and `height` must be even, `data` holds `width * height * 3 / 2` bytes
+/// and `rgb` receives `width * height * 3` bytes.
+pub fn yuv420_to_rgb(
+    data: &[u8],
+    format: YuvFormat,
+    width: i32,
+    height: i32,
+    rgb: &mut [u8],
+) -> anyhow::Result<()> {
+    let len = check_yuv420(data, width, height)?;
+    if rgb.len() != len {
+        anyhow::bail!("Expected rgb length {}, provided {}", len, rgb.len());
+    }
+    convert_yuv420(data, format, width, height, rgb);
+    Ok(())
+}
+
+impl Mat {
+    /// Constructs matrix from a YUV 4:2:0 frame.
+    ///
+    /// The frame is decoded as RGB, so `pixel_type` must be one of `RGB`, `RGBtoBGR`,
+    /// `RGBtoGRAY`, `RGBtoRGBA` or `RGBtoBGRA`.
+    pub fn from_yuv420(
+        data: &[u8],
+        format: YuvFormat,
+        width: i32,
+        height: i32,
+        pixel_type: MatPixelType,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        let rgb = yuv420_rgb(data, format, width, height, &pixel_type)?;
+        Mat::from_pixels(&rgb, pixel_type, width, height, alloc)
+    }
+
+    /// Constructs matrix from resizing a YUV 4:2:0 frame, see [Mat::from_yuv420].
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_yuv420_resize(
+        data: &[u8],
+        format: YuvFormat,
+        width: i32,
+        height: i32,
+        pixel_type: MatPixelType,
+        target_width: i32,
+        target_height: i32,
+        alloc: Option<&Allocator>,
+    ) -> anyhow::Result<Mat> {
+        let rgb = yuv420_rgb(data, format, width, height, &pixel_type)?;
+        Mat::from_pixels_resize(
+            &rgb,
+            pixel_type,
+            width,
+            height,
+            target_width,
+            target_height,
+            alloc,
+        )
+    }
+}
+
+/// Exif orientation of an image, see [kanna_rotate].
+#[cfg(feature = "pixel-rotate")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    /// Upright, nothing to do.
+    TopLeft = 1,
+    /// Mirrored horizontally.
+    TopRight = 2,
+    /// Rotated by 180 degrees.
+    BottomRight = 3,
+    /// Mirrored vertically.
+    BottomLeft = 4,
+    /// Mirrored along the main diagonal.
+    LeftTop = 5,
+    /// Rotated by 90 degrees clockwise to be upright.
+    RightTop = 6,
+    /// Mirrored along the anti-diagonal.
+    RightBottom = 7,
+    /// Rotated by 90 degrees counter-clockwise to be upright.
+    LeftBottom = 8,
+}
+
+#[cfg(feature = "pixel-rotate")]
+impl Orientation {
+    /// Maps an Exif orientation tag value, 1 to 8.
+    pub fn from_exif(value: u16) -> Option<Self> {
+        Some(match value {
+            1 => Orientation::TopLeft,
+            2 => Orientation::TopRight,
+            3 => Orientation::BottomRight,
+            4 => Orientation::BottomLeft,
+            5 => Orientation::LeftTop,
+            6 => Orientation::RightTop,
+            7 => Orientation::RightBottom,
+            8 => Orientation::LeftBottom,
+            _ => return None,
+        })
+    }
+
+    /// Size of the upright image, width and height are swapped for orientations 5 to 8.
+    pub fn upright_size(self, width: i32, height: i32) -> (i32, i32) {
+        if self as i32 >= 5 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+/// Rotates or flips `channels`-channel pixels of the given orientation to be upright.
+///
+/// `dst` receives an image of [Orientation::upright_size].
+#[cfg(feature = "pixel-rotate")]
+pub fn kanna_rotate(
+    src: &[u8],
+    width: i32,
+    height: i32,
+    channels: i32,
+    dst: &mut [u8],
+    orientation: Orientation,
+) -> anyhow::Result<()> {
+    let src_stride = check_image(src, width, height, channels)?;
+    let (w, h) = orientation.upright_size(width, height);
+    let dst_stride = check_image(dst, w, h, channels)?;
+
+    unsafe {
+        ncnn_ext_kanna_rotate(
+            src.as_ptr(),
+            width,
+            height,
+            src_stride,
+            dst.as_mut_ptr(),
+            w,
+            h,
+            dst_stride,
+            channels,
+            orientation as _,
+        )
+    };
+    Ok(())
+}
+
+/// Estimates the 2x3 similarity transform mapping `points_from` onto `points_to`.
+///
+/// Typical use is face alignment, mapping detected landmarks onto a reference template.
+#[cfg(feature = "pixel-affine")]
+pub fn get_affine_transform(
+    points_from: &[[f32; 2]],
+    points_to: &[[f32; 2]],
+) -> anyhow::Result<[f32; 6]> {
+    if points_from.len() != points_to.len() {
+        anyhow::bail!(
+            "Point count mismatch, {} and {}",
+            points_from.len(),
+            points_to.len()
+        );
+    }
+    if points_from.len() < 2 {
+        anyhow::bail!("Expected at least 2 points, got {}", points_from.len());
+    }
+
+    let mut tm = [0.0; 6];
+    unsafe {
+        ncnn_ext_get_affine_transform(
+            points_from.as_ptr() as _,
+            points_to.as_ptr() as _,
+            points_from.len() as _,
+            tm.as_mut_ptr(),
+        )
+    };
+    Ok(tm)
+}
+
+/// Inverts a 2x3 affine transform.
+#[cfg(feature = "pixel-affine")]
+pub fn invert_affine_transform(tm: &[f32; 6]) -> [f32; 6] {
+    let mut tm_inv = [0.0; 6];
+    unsafe { ncnn_ext_invert_affine_transform(tm.as_ptr(), tm_inv.as_mut_ptr()) };
+    tm_inv
+}
+
+/// How [warpaffine_bilinear] fills destination pixels mapped from outside the source.
+#[cfg(feature = "pixel-affine")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarpBorder {
+    /// Fill with a color, packed as `0xAABBGGRR` with the first channel in the lowest byte.
+    Constant(u32),
+    /// Keep the destination pixels unchanged.
+    Transparent,
+}
+
+/// Warps `channels`-channel pixels with bilinear interpolation.
+///
+/// `tm` maps destination coordinates to source coordinates, use [invert_affine_transform]
+/// on a transform from [get_affine_transform] to warp source onto the target points.
+#[cfg(feature = "pixel-affine")]
+#[allow(clippy::too_many_arguments)]
+pub fn warpaffine_bilinear(
+    src: &[u8],
+    width: i32,
+    height: i32,
+    channels: i32,
+    dst: &mut [u8],
+    target_width: i32,
+    target_height: i32,
+    tm: &[f32; 6],
+    border: WarpBorder,
+) -> anyhow::Result<()> {
+    let src_stride = check_image(src, width, height, channels)?;
+    let dst_stride = check_image(dst, target_width, target_height, channels)?;
+
+    // Border types of ncnn::warpaffine_bilinear, BORDER_CONSTANT and BORDER_TRANSPARENT.
+    let (border_type, value) = match border {
+        WarpBorder::Constant(v) => (0, v),
+        WarpBorder::Transparent => (-233, 0),
+    };
+    unsafe {
+        ncnn_ext_warpaffine_bilinear(
+            src.as_ptr(),
+            width,
+            height,
+            src_stride,
+            dst.as_mut_ptr(),
+            target_width,
+            target_height,
+            dst_stride,
+            channels,
+            tm.as_ptr(),
+            border_type,
+            value,
+        )
+    };
+    Ok(())
+}
+
+fn yuv420_rgb(
+    data: &[u8],
+    format: YuvFormat,
+    width: i32,
+    height: i32,
+    pixel_type: &MatPixelType,
+) -> anyhow::Result<Vec<u8>> {
+    match pixel_type {
+        MatPixelType::RGB
+        | MatPixelType::RGBtoBGR
+        | MatPixelType::RGBtoGRAY
+        | MatPixelType::RGBtoRGBA
+        | MatPixelType::RGBtoBGRA => {}
+        _ => anyhow::bail!("YUV frames decode to RGB, pixel type must convert from RGB"),
+    }
+
+    let mut rgb = vec![0u8; check_yuv420(data, width, height)?];
+    convert_yuv420(data, format, width, height, &mut rgb);
+    Ok(rgb)
+}
+
+/// Returns the length of the frame decoded as RGB.
+fn check_yuv420(data: &[u8], width: i32, height: i32) -> anyhow::Result<usize> {
+    if width <= 0 || height <= 0 || width % 2 != 0 || height % 2 != 0 {
+        anyhow::bail!(
+            "YUV 4:2:0 frame size must be even, got {}x{}",
+            width,
+            height
+        );
+    }
+    let luma = (width as usize).checked_mul(height as usize);
+    let (len, rgb_len) = luma
+        .and_then(|luma| Some((luma.checked_add(luma / 2)?, luma.checked_mul(3)?)))
+        .ok_or_else(|| anyhow::anyhow!("YUV 4:2:0 frame {}x{} is too large", width, height))?;
+    if data.len() != len {
+        anyhow::bail!("Expected data length {}, provided {}", len, data.len());
+    }
+    Ok(rgb_len)
+}
+
+/// Decodes a frame passed through [check_yuv420] into `rgb` of the returned length.
+fn convert_yuv420(data: &[u8], format: YuvFormat, width: i32, height: i32, rgb: &mut [u8]) {
+    match format {
+        YuvFormat::NV21 => unsafe {
+            ncnn_ext_yuv420sp2rgb(data.as_ptr(), width, height, rgb.as_mut_ptr())
+        },
+        YuvFormat::NV12 => unsafe {
+            ncnn_ext_yuv420sp2rgb_nv12(data.as_ptr(), width, height, rgb.as_mut_ptr())
+        },
+        YuvFormat::I420 => {
+            let nv12 = i420_to_nv12(data, rgb.len() / 3);
+            unsafe { ncnn_ext_yuv420sp2rgb_nv12(nv12.as_ptr(), width, height, rgb.as_mut_ptr()) }
+        }
+    }
+}
+
+/// Returns the row stride of an image of interleaved channels.
+#[cfg(any(
+    feature = "pixel-rotate",
+    feature = "pixel-affine",
+    feature = "pixel-drawing"
+))]
+fn check_image(data: &[u8], width: i32, height: i32, channels: i32) -> anyhow::Result<i32> {
+    if !(1..=4).contains(&channels) {
+        anyhow::bail!("Expected 1 to 4 channels, got {}", channels);
+    }
+    if width <= 0 || height <= 0 {
+        anyhow::bail!("Invalid image size {}x{}", width, height);
+    }
+    let stride = width
+        .checked_mul(channels)
+        .ok_or_else(|| anyhow::anyhow!("Image width {} is too large", width))?;
+    let len = (stride as usize)
+        .checked_mul(height as usize)
+        .ok_or_else(|| anyhow::anyhow!("Image size {}x{} is too large", width, height))?;
+    if data.len() != len {
+        anyhow::bail!("Expected data length {}, provided {}", len, data.len());
+    }
+    Ok(stride)
+}
+
+/// Interleaves the U and V planes of an I420 frame.
+fn i420_to_nv12(data: &[u8], luma: usize) -> Vec<u8> {
+    let chroma = luma / 4;
+    let (y, uv) = data.split_at(luma);
+    let (u, v) = uv.split_at(chroma);
+
+    let mut nv12 = Vec::with_capacity(data.len());
+    nv12.extend_from_slice(y);
+    for (u, v) in u.iter().zip(v) {
+        nv12.push(*u);
+        nv12.push(*v);
+    }
+    nv12
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yuv420_formats_agree() {
+        let (w, h) = (4, 2);
+        let y = [16u8, 60, 120, 235, 30, 90, 150, 200];
+        let (u, v) = ([90u8, 160], [200u8, 70]);
+
+        let nv21 = [&y[..], &[v[0], u[0], v[1], u[1]]].concat();
+        let nv12 = [&y[..], &[u[0], v[0], u[1], v[1]]].concat();
+        let i420 = [&y[..], &u, &v].concat();
+
+        let mut expected = vec![0u8; w * h * 3];
+        yuv420_to_rgb(&nv21, YuvFormat::NV21, 4, 2, &mut expected).unwrap();
+        for (data, format) in [(nv12, YuvFormat::NV12), (i420, YuvFormat::I420)] {
+            let mut rgb = vec![0u8; w * h * 3];
+            yuv420_to_rgb(&data, format, 4, 2, &mut rgb).unwrap();
+            assert_eq!(expected, rgb);
+        }
+
+        let mat = Mat::from_yuv420(&nv21, YuvFormat::NV21, 4, 2, MatPixelType::RGBtoBGR, None);
+        assert_eq!(3, mat.unwrap().c());
+        assert!(Mat::from_yuv420(&nv21, YuvFormat::NV21, 4, 2, MatPixelType::BGR, None).is_err());
+        assert!(yuv420_to_rgb(&nv21, YuvFormat::NV21, 3, 2, &mut expected).is_err());
+        assert!(yuv420_to_rgb(&nv21, YuvFormat::NV21, 1 << 16, 1 << 16, &mut expected).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "pixel-rotate")]
+    fn kanna_rotate_swaps_size() {
+        let src = [1u8, 2, 3, 4, 5, 6];
+        let mut dst = [0u8; 6];
+        kanna_rotate(&src, 3, 2, 1, &mut dst, Orientation::BottomRight).unwrap();
+        assert_eq!([6, 5, 4, 3, 2, 1], dst);
+
+        assert_eq!((2, 3), Orientation::RightTop.upright_size(3, 2));
+        assert!(kanna_rotate(&src, 3, 2, 1, &mut dst[..4], Orientation::RightTop).is_err());
+    }
+
+    #[test]
+    #[cfg(any(
+        feature = "pixel-rotate",
+        feature = "pixel-affine",
+        feature = "pixel-drawing"
+    ))]
+    fn image_size_overflow() {
+        // 65536x65536 wraps to 0 bytes in i32
+        assert!(check_image(&[], 1 << 16, 1 << 16, 1).is_err());
+        assert!(check_image(&[], i32::MAX, 1, 4).is_err());
+        assert_eq!(6, check_image(&[0; 12], 2, 2, 3).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "pixel-affine")]
+    fn affine_transform_round_trip() {
+        let from = [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]];
+        let to = [[5.0, 5.0], [25.0, 5.0], [5.0, 25.0]];
+        let tm = get_affine_transform(&from, &to).unwrap();
+        let inv = invert_affine_transform(&tm);
+        let expected = [0.5, 0.0, -2.5, 0.0, 0.5, -2.5];
+        for (v, e) in inv.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-4);
+        }
+        assert!(get_affine_transform(&from, &to[..2]).is_err());
+
+        let src = [200u8; 4 * 4 * 3];
+        let mut dst = [0u8; 2 * 2 * 3];
+        let border = WarpBorder::Constant(0);
+        warpaffine_bilinear(&src, 4, 4, 3, &mut dst, 2, 2, &inv, border).unwrap();
+        assert_eq!([200u8; 2 * 2 * 3], dst);
+    }
+}
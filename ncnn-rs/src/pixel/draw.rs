@@ -0,0 +1,172 @@
+//! Drawing primitives for visualising results on 8-bit pixel buffers.
+//!
+//! Colors hold one byte per channel with the first channel in the lowest byte,
+//! e.g. `0x0000ff` is red in an RGB buffer and blue in a BGR buffer.
+
+use super::check_image;
+use crate::mat::Rect;
+use ncnn_bind::*;
+use std::ffi::CString;
+
+/// Thickness which fills rectangles and circles instead of outlining them.
+pub const FILLED: i32 = -1;
+
+/// Mutable 8-bit pixel buffer of 1 to 4 interleaved channels.
+pub struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: i32,
+    height: i32,
+    channels: i32,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(
+        pixels: &'a mut [u8],
+        width: i32,
+        height: i32,
+        channels: i32,
+    ) -> anyhow::Result<Self> {
+        check_image(pixels, width, height, channels)?;
+        Ok(Self {
+            pixels,
+            width,
+            height,
+            channels,
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn channels(&self) -> i32 {
+        self.channels
+    }
+
+    /// Draws a rectangle outline of `thickness` pixels, or a filled one with [FILLED].
+    pub fn draw_rectangle(&mut self, rect: Rect, color: u32, thickness: i32) {
+        let draw = match self.channels {
+            1 => ncnn_draw_rectangle_c1,
+            2 => ncnn_draw_rectangle_c2,
+            3 => ncnn_draw_rectangle_c3,
+            _ => ncnn_draw_rectangle_c4,
+        };
+        unsafe {
+            draw(
+                self.pixels.as_mut_ptr(),
+                self.width,
+                self.height,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                color,
+                thickness,
+            )
+        };
+    }
+
+    /// Draws a circle outline of `thickness` pixels, or a filled one with [FILLED].
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, color: u32, thickness: i32) {
+        let draw = match self.channels {
+            1 => ncnn_draw_circle_c1,
+            2 => ncnn_draw_circle_c2,
+            3 => ncnn_draw_circle_c3,
+            _ => ncnn_draw_circle_c4,
+        };
+        unsafe {
+            draw(
+                self.pixels.as_mut_ptr(),
+                self.width,
+                self.height,
+                cx,
+                cy,
+                radius,
+                color,
+                thickness,
+            )
+        };
+    }
+
+    /// Draws a line of `thickness` pixels from `(x0, y0)` to `(x1, y1)`.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32, thickness: i32) {
+        let draw = match self.channels {
+            1 => ncnn_draw_line_c1,
+            2 => ncnn_draw_line_c2,
+            3 => ncnn_draw_line_c3,
+            _ => ncnn_draw_line_c4,
+        };
+        unsafe {
+            draw(
+                self.pixels.as_mut_ptr(),
+                self.width,
+                self.height,
+                x0,
+                y0,
+                x1,
+                y1,
+                color,
+                thickness,
+            )
+        };
+    }
+
+    /// Draws ASCII text with its top-left corner at `(x, y)`, `\n` starts a new line.
+    ///
+    /// `font_size` is the line height in pixels and must be positive.
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        font_size: i32,
+        color: u32,
+    ) -> anyhow::Result<()> {
+        if font_size <= 0 {
+            anyhow::bail!("Invalid font size {}", font_size);
+        }
+        let c_str = CString::new(text)?;
+        let draw = match self.channels {
+            1 => ncnn_draw_text_c1,
+            2 => ncnn_draw_text_c2,
+            3 => ncnn_draw_text_c3,
+            _ => ncnn_draw_text_c4,
+        };
+        unsafe {
+            draw(
+                self.pixels.as_mut_ptr(),
+                self.width,
+                self.height,
+                c_str.as_ptr(),
+                x,
+                y,
+                font_size,
+                color,
+            )
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_filled_rectangle() {
+        let mut pixels = vec![0u8; 4 * 4 * 3];
+        let mut canvas = Canvas::new(&mut pixels, 4, 4, 3).unwrap();
+        canvas.draw_rectangle(Rect::new(1, 1, 2, 2), 0x0000ff, FILLED);
+        canvas.draw_text("ok", 0, 0, 4, 0xffffff).unwrap();
+        assert!(canvas.draw_text("a\0b", 0, 0, 4, 0).is_err());
+        assert!(canvas.draw_text("ok", 0, 0, 0, 0).is_err());
+
+        assert_eq!([0xff, 0, 0], pixels[(2 * 4 + 2) * 3..(2 * 4 + 3) * 3]);
+        assert!(Canvas::new(&mut pixels, 4, 4, 4).is_err());
+        assert!(Canvas::new(&mut [], 1 << 16, 1 << 16, 1).is_err());
+    }
+}